@@ -0,0 +1,121 @@
+/*
+ * Copyright 2015 blake2-rfc Developers
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+/*
+ * C API of blake2-rfc, built with the "capi" cargo feature. It is
+ * compatible with the blake2.h header of libb2, and matches the
+ * declarations in src/capi.rs; tests/capi.rs checks the layout of its
+ * types and the types of its functions against them.
+ */
+
+#ifndef BLAKE2_H
+#define BLAKE2_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#if defined(_MSC_VER)
+#define BLAKE2_PACKED(x) __pragma(pack(push, 1)) x __pragma(pack(pop))
+#define BLAKE2_ALIGN(x) __declspec(align(x))
+#else
+#define BLAKE2_PACKED(x) x __attribute__((packed))
+#define BLAKE2_ALIGN(x) __attribute__((aligned(x)))
+#endif
+
+#if defined(__cplusplus)
+extern "C" {
+#endif
+
+enum blake2s_constant {
+  BLAKE2S_BLOCKBYTES = 64,
+  BLAKE2S_OUTBYTES = 32,
+  BLAKE2S_KEYBYTES = 32,
+  BLAKE2S_SALTBYTES = 8,
+  BLAKE2S_PERSONALBYTES = 8
+};
+
+enum blake2b_constant {
+  BLAKE2B_BLOCKBYTES = 128,
+  BLAKE2B_OUTBYTES = 64,
+  BLAKE2B_KEYBYTES = 64,
+  BLAKE2B_SALTBYTES = 16,
+  BLAKE2B_PERSONALBYTES = 16
+};
+
+BLAKE2_PACKED(struct blake2s_param__ {
+  uint8_t digest_length;
+  uint8_t key_length;
+  uint8_t fanout;
+  uint8_t depth;
+  uint32_t leaf_length;
+  uint8_t node_offset[6];
+  uint8_t node_depth;
+  uint8_t inner_length;
+  uint8_t salt[BLAKE2S_SALTBYTES];
+  uint8_t personal[BLAKE2S_PERSONALBYTES];
+});
+typedef struct blake2s_param__ blake2s_param;
+
+BLAKE2_PACKED(struct blake2b_param__ {
+  uint8_t digest_length;
+  uint8_t key_length;
+  uint8_t fanout;
+  uint8_t depth;
+  uint32_t leaf_length;
+  uint64_t node_offset;
+  uint8_t node_depth;
+  uint8_t inner_length;
+  uint8_t reserved[14];
+  uint8_t salt[BLAKE2B_SALTBYTES];
+  uint8_t personal[BLAKE2B_PERSONALBYTES];
+});
+typedef struct blake2b_param__ blake2b_param;
+
+/* The states have the size and alignment of libb2, but are opaque. */
+typedef struct BLAKE2_ALIGN(64) blake2s_state__ {
+  uint8_t opaque[192];
+} blake2s_state;
+
+typedef struct BLAKE2_ALIGN(64) blake2b_state__ {
+  uint8_t opaque[384];
+} blake2b_state;
+
+int blake2s_init(blake2s_state *S, size_t outlen);
+int blake2s_init_key(blake2s_state *S, size_t outlen, const void *key,
+                     size_t keylen);
+int blake2s_init_param(blake2s_state *S, const blake2s_param *P);
+int blake2s_update(blake2s_state *S, const uint8_t *in, size_t inlen);
+int blake2s_final(blake2s_state *S, uint8_t *out, size_t outlen);
+
+int blake2b_init(blake2b_state *S, size_t outlen);
+int blake2b_init_key(blake2b_state *S, size_t outlen, const void *key,
+                     size_t keylen);
+int blake2b_init_param(blake2b_state *S, const blake2b_param *P);
+int blake2b_update(blake2b_state *S, const uint8_t *in, size_t inlen);
+int blake2b_final(blake2b_state *S, uint8_t *out, size_t outlen);
+
+int blake2s(uint8_t *out, const void *in, const void *key, size_t outlen,
+            size_t inlen, size_t keylen);
+int blake2b(uint8_t *out, const void *in, const void *key, size_t outlen,
+            size_t inlen, size_t keylen);
+int blake2sp(uint8_t *out, const void *in, const void *key, size_t outlen,
+             size_t inlen, size_t keylen);
+int blake2bp(uint8_t *out, const void *in, const void *key, size_t outlen,
+             size_t inlen, size_t keylen);
+
+static inline int blake2(uint8_t *out, const void *in, const void *key,
+                         size_t outlen, size_t inlen, size_t keylen) {
+  return blake2b(out, in, key, outlen, inlen, keylen);
+}
+
+#if defined(__cplusplus)
+}
+#endif
+
+#endif
@@ -0,0 +1,294 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Hashing of asynchronous readers.
+//!
+//! With the `futures-io` cargo feature, the `futures` module hashes
+//! readers implementing `futures_io::AsyncRead`; with the `tokio` cargo
+//! feature, the `tokio` module hashes readers implementing
+//! `tokio::io::AsyncRead`. The hashing contexts and the `HashingReader`
+//! and `HashingWriter` adapters also implement the corresponding
+//! asynchronous traits.
+//!
+//! Hashing is done within `poll`, so to avoid starving other tasks on
+//! the same executor, no more than `BATCH_LEN` bytes are hashed before
+//! yielding to the executor.
+
+/// Maximum number of bytes hashed in a single poll.
+pub const BATCH_LEN: usize = ::file::BUF_LEN;
+
+fn identity<S>(state: S) -> S { state }
+
+macro_rules! async_reader_impl {
+    ($read:path, $poll_read:expr) => {
+        use std::fmt;
+        use std::io::{self, Write};
+        use std::vec::Vec;
+        use core::future::Future;
+        use core::pin::Pin;
+        use core::task::{Context, Poll};
+
+        use super::BATCH_LEN;
+
+        /// Future which hashes everything read from a reader.
+        ///
+        /// Resolves to the result of calling the finalization function
+        /// with the hashing context.
+        pub struct HashAsyncReader<S, R, T> {
+            state: Option<S>,
+            reader: R,
+            buf: Vec<u8>,
+            finish: fn(S) -> T,
+        }
+
+        impl<S, R, T> HashAsyncReader<S, R, T> {
+            /// Creates a future which writes everything read from a
+            /// reader into a hashing context, and then calls `finish`.
+            pub fn new(state: S, reader: R, finish: fn(S) -> T) -> Self {
+                HashAsyncReader {
+                    state: Some(state),
+                    reader,
+                    buf: vec![0; BATCH_LEN],
+                    finish,
+                }
+            }
+        }
+
+        impl<S, R, T> fmt::Debug for HashAsyncReader<S, R, T>
+            where S: fmt::Debug, R: fmt::Debug
+        {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct("HashAsyncReader")
+                    .field("state", &self.state)
+                    .field("reader", &self.reader)
+                    .finish()
+            }
+        }
+
+        impl<S, R, T> Future for HashAsyncReader<S, R, T>
+            where S: Write + Unpin, R: $read + Unpin
+        {
+            type Output = io::Result<T>;
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+                let this = self.get_mut();
+                let state = this.state.as_mut()
+                    .expect("HashAsyncReader polled after completion");
+
+                let len = match $poll_read(Pin::new(&mut this.reader), cx,
+                                           &mut this.buf[..]) {
+                    Poll::Ready(Ok(0)) => {
+                        let state = this.state.take().unwrap();
+                        return Poll::Ready(Ok((this.finish)(state)));
+                    }
+                    Poll::Ready(Ok(len)) => len,
+                    Poll::Ready(Err(ref e))
+                        if e.kind() == io::ErrorKind::Interrupted => 0,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                if let Err(e) = state.write_all(&this.buf[..len]) {
+                    return Poll::Ready(Err(e));
+                }
+
+                // Yield after each batch, to be polled again as soon as
+                // possible.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+
+        /// Writes everything read from a reader into a hashing context.
+        ///
+        /// The returned future resolves to the hashing context.
+        pub fn update_reader<S, R>(state: S, reader: R)
+                                   -> HashAsyncReader<S, R, S>
+            where S: Write + Unpin, R: $read + Unpin
+        {
+            HashAsyncReader::new(state, reader, super::identity)
+        }
+    }
+}
+
+/// Hashing of `futures_io::AsyncRead` readers.
+#[cfg(feature = "futures-io")]
+pub mod futures {
+    fn poll_read<R>(reader: Pin<&mut R>, cx: &mut Context, buf: &mut [u8])
+                    -> Poll<io::Result<usize>>
+        where R: ::futures_io::AsyncRead
+    {
+        reader.poll_read(cx, buf)
+    }
+
+    async_reader_impl!(::futures_io::AsyncRead, poll_read);
+}
+
+/// Hashing of `tokio::io::AsyncRead` readers.
+#[cfg(feature = "tokio")]
+pub mod tokio {
+    fn poll_read<R>(reader: Pin<&mut R>, cx: &mut Context, buf: &mut [u8])
+                    -> Poll<io::Result<usize>>
+        where R: ::tokio::io::AsyncRead
+    {
+        let mut buf = ::tokio::io::ReadBuf::new(buf);
+        match reader.poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    async_reader_impl!(::tokio::io::AsyncRead, poll_read);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::ptr;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use blake2::selftest_seq;
+    use blake2b::{self, Blake2b, blake2b};
+    use blake2s::{self, Blake2s, blake2s};
+    use super::BATCH_LEN;
+
+    fn input(len: usize) -> Vec<u8> {
+        selftest_seq(1024).iter().cloned().cycle().take(len).collect()
+    }
+
+    /// Returns a waker which does nothing.
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker { RawWaker::new(ptr::null(), &VTABLE) }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+    }
+
+    /// Polls until ready, returning the result and the number of polls.
+    fn block_on<F: Future + Unpin>(mut future: F) -> (F::Output, usize) {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut polls = 1;
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut cx) {
+                return (output, polls);
+            }
+            polls += 1;
+        }
+    }
+
+    /// Calls a poll function until ready.
+    fn poll_fn<T, F>(mut f: F) -> T
+        where F: FnMut(&mut Context) -> Poll<T>
+    {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = f(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn test_futures_reader() {
+        use super::futures::update_reader;
+
+        let data = input(3 * BATCH_LEN + 5);
+        let (hash, polls) = block_on(
+            blake2b::hash_async_reader(64, b"key", &data[..]));
+        assert_eq!(hash.unwrap(), blake2b(64, b"key", &data));
+        assert_eq!(polls, 5);
+
+        let (state, _) = block_on(update_reader(Blake2s::new(32), &data[..]));
+        assert_eq!(state.unwrap().finalize(), blake2s(32, &[], &data));
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn test_futures_adapters() {
+        use futures_io::{AsyncRead, AsyncWrite};
+
+        let data = input(BATCH_LEN + 5);
+        let expected = blake2b(64, &[], &data);
+
+        let mut reader = blake2b::HashingReader::verifying(
+            &data[..], Blake2b::new(64), &expected);
+        let mut buf = [0; 1000];
+        while poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf))
+                  .unwrap() != 0 {}
+        assert_eq!(reader.into_inner_and_result().1, expected);
+
+        let mut writer = blake2b::HashingWriter::verifying(
+            Vec::new(), Blake2b::new(64), &expected);
+        let mut state = Blake2b::new(64);
+        for part in data.chunks(1000) {
+            let len = poll_fn(|cx| Pin::new(&mut writer).poll_write(cx, part));
+            assert_eq!(len.unwrap(), part.len());
+            let len = poll_fn(|cx| Pin::new(&mut state).poll_write(cx, part));
+            assert_eq!(len.unwrap(), part.len());
+        }
+        poll_fn(|cx| Pin::new(&mut writer).poll_close(cx)).unwrap();
+        assert_eq!(writer.into_verified_inner().unwrap(), data);
+        assert_eq!(state.finalize(), expected);
+
+        let mut state = Blake2b::new(64);
+        let len = poll_fn(|cx| Pin::new(&mut state).poll_write(cx, &data));
+        assert_eq!(len.unwrap(), BATCH_LEN);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_reader() {
+        use super::tokio::update_reader;
+
+        let data = input(3 * BATCH_LEN + 5);
+        let (hash, polls) = block_on(
+            blake2s::hash_tokio_reader(32, b"key", &data[..]));
+        assert_eq!(hash.unwrap(), blake2s(32, b"key", &data));
+        assert_eq!(polls, 5);
+
+        let (state, _) = block_on(update_reader(Blake2b::new(64), &data[..]));
+        assert_eq!(state.unwrap().finalize(), blake2b(64, &[], &data));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_adapters() {
+        use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+        let data = input(BATCH_LEN + 5);
+        let expected = blake2s(32, &[], &data);
+
+        let mut reader = blake2s::HashingReader::verifying(
+            &data[..], Blake2s::new(32), &blake2s::Blake2sResult::from_bytes(&[0; 32]));
+        let mut buf = [0; 1000];
+        let err = loop {
+            let mut buf = ReadBuf::new(&mut buf);
+            match poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf)) {
+                Ok(()) => assert!(!buf.filled().is_empty()),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(err.kind(), ::std::io::ErrorKind::InvalidData);
+
+        let mut writer = blake2s::HashingWriter::new(Vec::new(),
+                                                     Blake2s::new(32));
+        for part in data.chunks(1000) {
+            let len = poll_fn(|cx| Pin::new(&mut writer).poll_write(cx, part));
+            assert_eq!(len.unwrap(), part.len());
+        }
+        poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx)).unwrap();
+        let (inner, result) = writer.into_inner_and_result();
+        assert_eq!(inner, data);
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+// The original BLAKE, the SHA-3 finalist which BLAKE2 is based on.
+//
+// BLAKE uses the same G function and SIGMA permutations as BLAKE2, but
+// XORs each message word with a constant, reads the message as
+// big-endian words, has an optional salt, counts the message bits
+// instead of the bytes, and pads the message as in SHA-2. Its round is
+// the round of BLAKE2s or BLAKE2b on the message words XORed with the
+// constants, with other rotations for BLAKE-512.
+
+macro_rules! blake_impl {
+    ($state:ident, $result:ident, $func:ident, $blake2:ident, $word:ident,
+     $vec:ident, $count:ident, $bytes:expr, $trunc:expr, $rounds:expr,
+     $R1:expr, $R2:expr, $R3:expr, $R4:expr,
+     $IV:expr, $TRUNC_IV:expr, $C:expr) => {
+        use core::mem;
+
+        #[cfg(feature = "std")]
+        use std::io;
+
+        use $crate::constant_time_eq::constant_time_eq;
+        use $crate::simd::$vec;
+
+        /// Container for a hash result.
+        ///
+        /// This container uses a constant-time comparison for equality.
+        /// If a constant-time comparison is not necessary, the hash
+        /// result can be extracted with the `as_bytes` method.
+        #[derive(Clone, Copy, Debug)]
+        pub struct $result {
+            h: [u8; $bytes],
+            nn: usize,
+        }
+
+        #[cfg_attr(feature = "cargo-clippy", allow(len_without_is_empty))]
+        impl $result {
+            /// Returns the contained hash result as a byte string.
+            #[inline]
+            pub fn as_bytes(&self) -> &[u8] { &self.h[..self.nn] }
+
+            /// Returns the length of the hash result.
+            ///
+            /// This is the same value that was used to create the hash
+            /// context.
+            #[inline]
+            pub fn len(&self) -> usize { self.nn }
+        }
+
+        impl AsRef<[u8]> for $result {
+            #[inline]
+            fn as_ref(&self) -> &[u8] { self.as_bytes() }
+        }
+
+        impl Eq for $result { }
+
+        impl PartialEq for $result {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                constant_time_eq(self.as_bytes(), other.as_bytes())
+            }
+        }
+
+        impl PartialEq<[u8]> for $result {
+            #[inline]
+            fn eq(&self, other: &[u8]) -> bool {
+                constant_time_eq(self.as_bytes(), other)
+            }
+        }
+
+        /// State context.
+        #[derive(Clone, Debug)]
+        pub struct $state {
+            h: [$vec; 2],
+            s: $vec,
+            buf: [u8; $bytes * 2],
+            buflen: usize,
+            t: $count,
+            nn: usize,
+        }
+
+        const IV: [$word; 8] = $IV;
+        const TRUNC_IV: [$word; 8] = $TRUNC_IV;
+        const C: [$word; 16] = $C;
+
+        /// Length of the message length at the end of the padding.
+        const LEN_BYTES: usize = mem::size_of::<$count>();
+
+        /// Convenience function for all-in-one computation.
+        pub fn $func(nn: usize, data: &[u8]) -> $result {
+            let mut state = $state::new(nn);
+            state.update(data);
+            state.finalize()
+        }
+
+        impl $state {
+            #[doc = concat!("Creates a new hashing context, for a ", stringify!($bytes),
+                            "-byte or a ", stringify!($trunc), "-byte output.")]
+            pub fn new(nn: usize) -> Self {
+                Self::with_salt(nn, &[0; $bytes / 2])
+            }
+
+            /// Creates a new hashing context with a salt.
+            pub fn with_salt(nn: usize, salt: &[u8; $bytes / 2]) -> Self {
+                assert!(nn == $bytes || nn == $trunc);
+                let iv = if nn == $bytes { &IV } else { &TRUNC_IV };
+                let mut s = [0; 4];
+                for (word, bytes) in s.iter_mut().zip(salt.chunks(mem::size_of::<$word>())) {
+                    *word = Self::load_word(bytes);
+                }
+
+                $state {
+                    h: [$vec::new(iv[0], iv[1], iv[2], iv[3]),
+                        $vec::new(iv[4], iv[5], iv[6], iv[7])],
+                    s: $vec::new(s[0], s[1], s[2], s[3]),
+                    buf: [0; $bytes * 2],
+                    buflen: 0,
+                    t: 0,
+                    nn,
+                }
+            }
+
+            /// Updates the hashing context with more data.
+            pub fn update(&mut self, data: &[u8]) {
+                let mut rest = data;
+
+                if self.buflen > 0 {
+                    let len = ::core::cmp::min($bytes * 2 - self.buflen, rest.len());
+                    self.buf[self.buflen..self.buflen + len].copy_from_slice(&rest[..len]);
+                    self.buflen += len;
+                    rest = &rest[len..];
+
+                    if self.buflen < $bytes * 2 {
+                        return;
+                    }
+                    let block = self.buf;
+                    self.compress_message(&block);
+                    self.buflen = 0;
+                }
+
+                while rest.len() >= $bytes * 2 {
+                    let (block, tail) = rest.split_at($bytes * 2);
+                    self.compress_message(block);
+                    rest = tail;
+                }
+
+                self.buf[..rest.len()].copy_from_slice(rest);
+                self.buflen = rest.len();
+            }
+
+            /// Consumes the hashing context and returns the resulting hash.
+            pub fn finalize(mut self) -> $result {
+                let len = self.t.checked_add(self.buflen as $count * 8)
+                    .expect("hash data length overflow");
+                let one = if self.nn == $bytes { 0x01 } else { 0x00 };
+                let len_at = $bytes * 2 - LEN_BYTES;
+
+                let mut block = [0; $bytes * 2];
+                block[..self.buflen].copy_from_slice(&self.buf[..self.buflen]);
+                block[self.buflen] = 0x80;
+                if self.buflen < len_at {
+                    // A block without message bits has a zero counter.
+                    let t = if self.buflen == 0 { 0 } else { len };
+                    block[len_at - 1] |= one;
+                    block[len_at..].copy_from_slice(&len.to_be_bytes());
+                    self.compress(&block, t);
+                } else {
+                    self.compress(&block, len);
+                    let mut block = [0; $bytes * 2];
+                    block[len_at - 1] = one;
+                    block[len_at..].copy_from_slice(&len.to_be_bytes());
+                    self.compress(&block, 0);
+                }
+
+                let mut h = [0; $bytes];
+                let words = [self.h[0].0, self.h[0].1, self.h[0].2, self.h[0].3,
+                             self.h[1].0, self.h[1].1, self.h[1].2, self.h[1].3];
+                for (bytes, word) in h.chunks_mut(mem::size_of::<$word>()).zip(&words) {
+                    bytes.copy_from_slice(&word.to_be_bytes());
+                }
+                $result { h, nn: self.nn }
+            }
+
+            #[inline(always)]
+            fn load_word(bytes: &[u8]) -> $word {
+                let mut buf = [0; mem::size_of::<$word>()];
+                buf.copy_from_slice(bytes);
+                $word::from_be_bytes(buf)
+            }
+
+            fn compress_message(&mut self, block: &[u8]) {
+                self.t = self.t.checked_add($bytes * 2 * 8)
+                    .expect("hash data length overflow");
+                let t = self.t;
+                self.compress(block, t);
+            }
+
+            /// Applies one round, which is the BLAKE2 round with each
+            /// message word XORed with a constant.
+            #[inline(always)]
+            fn round(v: &mut [$vec; 4], m: &[$word; 16], s: &[usize; 16]) {
+                use $crate::blake2::SIGMA;
+
+                // The words in the order of this round, as little-endian
+                // words like the message of BLAKE2.
+                let mut x = [0; 16];
+                for i in 0..8 {
+                    x[2 * i] = (m[s[2 * i]] ^ C[s[2 * i + 1]]).to_le();
+                    x[2 * i + 1] = (m[s[2 * i + 1]] ^ C[s[2 * i]]).to_le();
+                }
+                $blake2::round_with_rotations(v, &x, &SIGMA[0], [$R1, $R2, $R3, $R4]);
+            }
+
+            fn compress(&mut self, block: &[u8], t: $count) {
+                use $crate::blake2::SIGMA;
+
+                let mut m = [0; 16];
+                for (word, bytes) in m.iter_mut().zip(block.chunks(mem::size_of::<$word>())) {
+                    *word = Self::load_word(bytes);
+                }
+
+                let t0 = t as $word;
+                let t1 = (t >> $word::BITS) as $word;
+                let h = &mut self.h;
+                let mut v = [
+                    h[0],
+                    h[1],
+                    self.s ^ $vec::new(C[0], C[1], C[2], C[3]),
+                    $vec::new(t0 ^ C[4], t0 ^ C[5], t1 ^ C[6], t1 ^ C[7]),
+                ];
+
+                for r in 0..$rounds {
+                    $state::round(&mut v, &m, &SIGMA[r % 10]);
+                }
+
+                h[0] = h[0] ^ self.s ^ v[0] ^ v[2];
+                h[1] = h[1] ^ self.s ^ v[1] ^ v[3];
+            }
+        }
+
+        impl Default for $state {
+            fn default() -> Self {
+                Self::new($bytes)
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl io::Write for $state {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.update(buf);
+                Ok(buf.len())
+            }
+
+            #[inline]
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    }
+}
@@ -21,9 +21,13 @@ pub const SIGMA: [[usize; 16]; 10] = [
 ];
 
 macro_rules! blake2_impl {
-    ($state:ident, $result:ident, $func:ident, $word:ident, $vec:ident,
-     $bytes:expr, $R1:expr, $R2:expr, $R3:expr, $R4:expr, $IV:expr) => {
+    ($state:ident, $result:ident, $key:ident, $resettable:ident, $func:ident,
+     $const_func:ident, $word:ident, $vec:ident, $bytes:expr,
+     $R1:expr, $R2:expr, $R3:expr, $R4:expr, $IV:expr) => {
         use core::cmp;
+        use core::convert::TryFrom;
+        use core::fmt;
+        use core::str::FromStr;
 
         #[cfg(feature = "std")]
         use std::io;
@@ -38,6 +42,10 @@ macro_rules! blake2_impl {
         /// This container uses a constant-time comparison for equality.
         /// If a constant-time comparison is not necessary, the hash
         /// result can be extracted with the `as_bytes` method.
+        ///
+        /// With the `zeroize` cargo feature, it implements `Zeroize`, but
+        /// as it is `Copy`, it is not wiped when dropped; to wipe it,
+        /// wrap it in `zeroize::Zeroizing`.
         #[derive(Clone, Copy, Debug)]
         pub struct $result {
             h: [$vec; 2],
@@ -56,6 +64,40 @@ macro_rules! blake2_impl {
             /// context.
             #[inline]
             pub fn len(&self) -> usize { self.nn }
+
+            /// Creates a hash result from its byte string.
+            pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+                assert!((1..=$bytes).contains(&bytes.len()));
+                let mut h = [$vec::new(0, 0, 0, 0); 2];
+                h.as_mut_bytes().copy_bytes_from(bytes);
+                $result { h, nn: bytes.len() }
+            }
+        }
+
+        impl $result {
+            /// Returns a value which formats the hash result as base64.
+            pub fn base64(&self) -> $crate::encoding::Base64<'_> {
+                $crate::encoding::base64(self.as_bytes())
+            }
+
+            /// Returns a value which formats the hash result as base32.
+            pub fn base32(&self) -> $crate::encoding::Base32<'_> {
+                $crate::encoding::base32(self.as_bytes())
+            }
+
+            /// Parses a hash result from base64.
+            pub fn from_base64(s: &str) -> Result<Self, $crate::encoding::Error> {
+                let mut buf = [0; $bytes];
+                let len = $crate::encoding::decode_base64(s, &mut buf)?;
+                Self::try_from(&buf[..len])
+            }
+
+            /// Parses a hash result from base32.
+            pub fn from_base32(s: &str) -> Result<Self, $crate::encoding::Error> {
+                let mut buf = [0; $bytes];
+                let len = $crate::encoding::decode_base32(s, &mut buf)?;
+                Self::try_from(&buf[..len])
+            }
         }
 
         impl AsRef<[u8]> for $result {
@@ -63,6 +105,46 @@ macro_rules! blake2_impl {
             fn as_ref(&self) -> &[u8] { self.as_bytes() }
         }
 
+        impl<'a> TryFrom<&'a [u8]> for $result {
+            type Error = $crate::encoding::Error;
+
+            fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+                if bytes.is_empty() || bytes.len() > $bytes {
+                    return Err($crate::encoding::Error::InvalidLength);
+                }
+                Ok(Self::from_bytes(bytes))
+            }
+        }
+
+        impl FromStr for $result {
+            type Err = $crate::encoding::Error;
+
+            /// Parses a hash result from hexadecimal, in either case.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut buf = [0; $bytes];
+                let len = $crate::encoding::decode_hex(s, &mut buf)?;
+                Self::try_from(&buf[..len])
+            }
+        }
+
+        impl fmt::Display for $result {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                $crate::encoding::encode_hex(self.as_bytes(), false, f)
+            }
+        }
+
+        impl fmt::LowerHex for $result {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                $crate::encoding::encode_hex(self.as_bytes(), false, f)
+            }
+        }
+
+        impl fmt::UpperHex for $result {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                $crate::encoding::encode_hex(self.as_bytes(), true, f)
+            }
+        }
+
         impl Eq for $result { }
 
         impl PartialEq for $result {
@@ -79,8 +161,19 @@ macro_rules! blake2_impl {
             }
         }
 
+        #[cfg(feature = "zeroize")]
+        impl $crate::zeroize::Zeroize for $result {
+            fn zeroize(&mut self) {
+                self.h.as_mut_bytes().zeroize();
+            }
+        }
+
         /// State context.
-        #[derive(Clone, Debug)]
+        ///
+        /// The `Debug` output doesn't include the buffered data, which
+        /// can contain the key. With the `zeroize` cargo feature, the
+        /// state is wiped when dropped.
+        #[derive(Clone)]
         pub struct $state {
             m: [$word; 16],
             h: [$vec; 2],
@@ -88,7 +181,289 @@ macro_rules! blake2_impl {
             nn: usize,
         }
 
-        const IV: [$word; 8] = $IV;
+        impl fmt::Debug for $state {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($state))
+                    .field("t", &self.t)
+                    .field("nn", &self.nn)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl Drop for $state {
+            fn drop(&mut self) {
+                use $crate::zeroize::Zeroize;
+                self.m.zeroize();
+                self.h.as_mut_bytes().zeroize();
+            }
+        }
+
+        /// Initial state of a resettable hashing context.
+        #[derive(Clone)]
+        struct Init {
+            /// Initial chaining value, before the key block if any.
+            h: [$vec; 2],
+            /// The key, padded to half a block, and the chaining value
+            /// after the key block.
+            key: Option<([u8; $bytes], [$vec; 2])>,
+            nn: u8,
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl Drop for Init {
+            fn drop(&mut self) {
+                use $crate::zeroize::Zeroize;
+                self.h.as_mut_bytes().zeroize();
+                if let Some((ref mut key, ref mut h)) = self.key {
+                    key.zeroize();
+                    h.as_mut_bytes().zeroize();
+                }
+            }
+        }
+
+        /// Parameter block, for the BLAKE2 features beyond the RFC.
+        ///
+        /// This builder sets the salt, the personalization and the tree
+        /// hashing parameters of a hashing context; the key length is
+        /// set by `to_state`. For tree hashing, the last node of each
+        /// level is finalized with the hidden `finalize_last_node`
+        /// method of the hashing context.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use blake2_rfc::", stringify!($func), "::Params;")]
+        ///
+        #[doc = concat!("let mut context = Params::new(", stringify!($bytes), ")")]
+        ///     .personal(b"my app")
+        ///     .to_state(Some(b"key"));
+        /// context.update(b"The quick brown fox jumps over the lazy dog");
+        /// let hash = context.finalize();
+        /// ```
+        #[derive(Clone, Copy, Debug)]
+        pub struct Params {
+            /// The fields, in the little-endian byte layout of the BLAKE2
+            /// specification.
+            p: [u8; $bytes],
+        }
+
+        #[allow(dead_code)]
+        impl Params {
+            const NODE_OFFSET_LEN: usize = match $bytes { 64 => 8, _ => 6 };
+            const NODE_DEPTH: usize = 8 + Self::NODE_OFFSET_LEN;
+            const SALT: usize = $bytes / 2;
+            const PERSONAL: usize = $bytes * 3 / 4;
+
+            /// Creates a parameter block for sequential mode, with an
+            /// `nn`-byte output.
+            pub fn new(nn: usize) -> Self {
+                assert!((1..=$bytes).contains(&nn));
+                let mut p = [0; $bytes];
+                p[0] = nn as u8;
+                p[2] = 1;
+                p[3] = 1;
+                Params { p }
+            }
+
+            pub(crate) fn key_length(mut self, kk: usize) -> Self {
+                assert!(kk <= $bytes);
+                self.p[1] = kk as u8;
+                self
+            }
+
+            /// Sets the fanout, or `0` for unlimited.
+            pub fn fanout(mut self, fanout: u8) -> Self {
+                self.p[2] = fanout;
+                self
+            }
+
+            /// Sets the maximal depth, or `255` for unlimited.
+            pub fn max_depth(mut self, depth: u8) -> Self {
+                self.p[3] = depth;
+                self
+            }
+
+            /// Sets the maximal byte length of the leaves, or `0` for
+            /// unlimited.
+            pub fn leaf_length(mut self, len: u32) -> Self {
+                self.p[4..8].copy_bytes_from(&len.to_le_bytes());
+                self
+            }
+
+            /// Sets the offset of the node within its level.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the offset doesn't fit in the parameter block
+            /// (6 bytes for BLAKE2s).
+            pub fn node_offset(mut self, offset: u64) -> Self {
+                let offset = offset.to_le_bytes();
+                assert!(offset[Self::NODE_OFFSET_LEN..].iter().all(|&b| b == 0));
+                self.p[8..Self::NODE_DEPTH]
+                    .copy_bytes_from(&offset[..Self::NODE_OFFSET_LEN]);
+                self
+            }
+
+            /// Sets the BLAKE2X output length, which overlaps the upper
+            /// bytes of the node offset.
+            pub(crate) fn xof_length(mut self, len: u32) -> Self {
+                const XOF_LEN: usize = match $bytes { 64 => 4, _ => 2 };
+                let len = len.to_le_bytes();
+                assert!(len[XOF_LEN..].iter().all(|&b| b == 0));
+                self.p[Self::NODE_DEPTH - XOF_LEN..Self::NODE_DEPTH]
+                    .copy_bytes_from(&len[..XOF_LEN]);
+                self
+            }
+
+            /// Sets the depth of the node, `0` for the leaves.
+            pub fn node_depth(mut self, depth: u8) -> Self {
+                self.p[Self::NODE_DEPTH] = depth;
+                self
+            }
+
+            /// Sets the byte length of the hashes of the inner nodes.
+            pub fn inner_length(mut self, len: u8) -> Self {
+                assert!(len as usize <= $bytes);
+                self.p[Self::NODE_DEPTH + 1] = len;
+                self
+            }
+
+            #[doc = concat!("Sets the salt, up to ", stringify!($bytes), " / 4 bytes,")]
+            /// padded with zeros.
+            pub fn salt(mut self, salt: &[u8]) -> Self {
+                assert!(salt.len() <= $bytes / 4);
+                self.p[Self::SALT..Self::PERSONAL].set_bytes(0);
+                self.p[Self::SALT..].copy_bytes_from(salt);
+                self
+            }
+
+            #[doc = concat!("Sets the personalization, up to ", stringify!($bytes), " / 4")]
+            /// bytes, padded with zeros.
+            pub fn personal(mut self, personal: &[u8]) -> Self {
+                assert!(personal.len() <= $bytes / 4);
+                self.p[Self::PERSONAL..].set_bytes(0);
+                self.p[Self::PERSONAL..].copy_bytes_from(personal);
+                self
+            }
+
+            /// Returns the parameter block as words.
+            pub(crate) fn to_words(self) -> [$word; 8] {
+                let mut words = [0; 8];
+                words.as_mut_bytes().copy_bytes_from(&self.p);
+                for word in &mut words {
+                    *word = $word::from_le(*word);
+                }
+                words
+            }
+
+            /// Creates a hashing context with these parameters and an
+            /// optional key.
+            pub fn to_state(self, k: Option<&[u8]>) -> $state {
+                let k = k.unwrap_or(&[]);
+                let mut state = $state::with_parameter_block(
+                    &self.key_length(k.len()).to_words());
+                state.set_key(k);
+                state
+            }
+
+            /// Hashes everything read from a reader, with these parameters
+            /// and an optional key.
+            ///
+            /// See the `file` module for more options.
+            #[cfg(feature = "std")]
+            pub fn hash_reader<R: io::Read>(self, k: Option<&[u8]>, mut reader: R)
+                                            -> io::Result<$result> {
+                let mut state = self.to_state(k);
+                $crate::file::update_reader(&mut state, &mut reader, None)?;
+                Ok(state.finalize())
+            }
+
+            /// Hashes the contents of a file, with these parameters and an
+            /// optional key.
+            ///
+            /// See the `file` module for more options.
+            #[cfg(feature = "std")]
+            pub fn hash_file<P: AsRef<::std::path::Path>>(self, k: Option<&[u8]>, path: P)
+                                                         -> io::Result<$result> {
+                let mut state = self.to_state(k);
+                $crate::file::update_file(&mut state, path, None)?;
+                Ok(state.finalize())
+            }
+
+            /// Creates a hashing context with an output length which
+            /// differs from the digest length in this parameter block.
+            pub(crate) fn to_state_with_output(self, k: &[u8], nn: usize)
+                                               -> $state {
+                assert!((1..=$bytes).contains(&nn));
+                let mut state = self.to_state(Some(k));
+                state.nn = nn;
+                state
+            }
+        }
+
+        pub(crate) const IV: [$word; 8] = $IV;
+
+        /// Converts a block to words, from little-endian byte order.
+        const fn words_from_le_bytes(block: &[u8; $bytes * 2]) -> [$word; 16] {
+            const W: usize = $bytes / 8;
+            let mut words = [0; 16];
+            let mut i = 0;
+            while i < $bytes * 2 {
+                words[i / W] |= (block[i] as $word) << (8 * (i % W));
+                i += 1;
+            }
+            words
+        }
+
+        /// Convenience function for all-in-one computation in const
+        /// context, with an `N`-byte output.
+        ///
+        /// This uses the scalar compression function, so at runtime it
+        /// is slower than the other functions, with the same results.
+        pub const fn $const_func<const N: usize>(k: &[u8], data: &[u8]) -> [u8; N] {
+            const BLOCK: usize = $bytes * 2;
+            const W: usize = $bytes / 8;
+            assert!(N >= 1 && N <= $bytes && k.len() <= $bytes);
+
+            let mut h = IV;
+            h[0] ^= 0x01010000 ^ ((k.len() as $word) << 8) ^ (N as $word);
+
+            let mut block = [0; BLOCK];
+            let mut len = 0;
+            let mut i = 0;
+            while i < k.len() {
+                block[i] = k[i];
+                i += 1;
+            }
+            if !k.is_empty() {
+                len = BLOCK;
+            }
+
+            let mut t = 0;
+            let mut i = 0;
+            while i < data.len() {
+                if len == BLOCK {
+                    t += BLOCK as u64;
+                    $state::compress_words(&mut h, &words_from_le_bytes(&block), t, 0, 0);
+                    block = [0; BLOCK];
+                    len = 0;
+                }
+                block[len] = data[i];
+                len += 1;
+                i += 1;
+            }
+            t += len as u64;
+            $state::compress_words(&mut h, &words_from_le_bytes(&block), t, !0, 0);
+
+            let mut out = [0; N];
+            let mut i = 0;
+            while i < N {
+                out[i] = (h[i / W] >> (8 * (i % W))) as u8;
+                i += 1;
+            }
+            out
+        }
 
         #[inline(always)]
         fn iv0() -> $vec { $vec::new(IV[0], IV[1], IV[2], IV[3]) }
@@ -102,28 +477,47 @@ macro_rules! blake2_impl {
             state.finalize()
         }
 
+        /// Convenience function for hashing everything read from a reader,
+        /// with an optional key.
+        ///
+        /// `Params::hash_reader` takes the other parameters, and the `file`
+        /// module has more options.
+        #[cfg(feature = "std")]
+        pub fn hash_reader<R: io::Read>(nn: usize, k: Option<&[u8]>, reader: R)
+                                        -> io::Result<$result> {
+            Params::new(nn).hash_reader(k, reader)
+        }
+
+        /// Convenience function for hashing the contents of a file, with an
+        /// optional key.
+        ///
+        /// `Params::hash_file` takes the other parameters, and the `file`
+        /// module has more options.
+        #[cfg(feature = "std")]
+        pub fn hash_file<P: AsRef<::std::path::Path>>(nn: usize, k: Option<&[u8]>, path: P)
+                                                     -> io::Result<$result> {
+            Params::new(nn).hash_file(k, path)
+        }
+
         impl $state {
             /// Creates a new hashing context without a key.
-            pub fn new(nn: usize) -> Self { Self::with_key(nn, &[]) }
+            ///
+            /// This can be used in const context.
+            pub const fn new(nn: usize) -> Self { Self::with_key(nn, &[]) }
 
             /// Creates a new hashing context with a key.
+            ///
+            /// This can be used in const context.
             #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation))]
-            pub fn with_key(nn: usize, k: &[u8]) -> Self {
+            pub const fn with_key(nn: usize, k: &[u8]) -> Self {
                 let kk = k.len();
                 assert!(nn >= 1 && nn <= $bytes && kk <= $bytes);
 
                 let p0 = 0x01010000 ^ ((kk as $word) << 8) ^ (nn as $word);
-                let mut state = $state {
-                    m: [0; 16],
-                    h: [iv0() ^ $vec::new(p0, 0, 0, 0), iv1()],
-                    t: 0,
-                    nn: nn,
-                };
-
-                if kk > 0 {
-                    state.m.as_mut_bytes().copy_bytes_from(k);
-                    state.t = $bytes * 2;
-                }
+                let h = [$vec::new(IV[0] ^ p0, IV[1], IV[2], IV[3]),
+                         $vec::new(IV[4], IV[5], IV[6], IV[7])];
+                let mut state = $state { m: [0; 16], h, t: 0, nn };
+                state.set_key(k);
                 state
             }
 
@@ -139,17 +533,46 @@ macro_rules! blake2_impl {
                     h: [iv0() ^ $vec::new(p[0], p[1], p[2], p[3]),
                         iv1() ^ $vec::new(p[4], p[5], p[6], p[7])],
                     t: 0,
-                    nn: nn,
+                    nn,
+                }
+            }
+
+            /// Buffers the padded key block of a new hashing context.
+            const fn set_key(&mut self, k: &[u8]) {
+                if !k.is_empty() {
+                    let mut block = [0; $bytes * 2];
+                    let mut i = 0;
+                    while i < k.len() {
+                        block[i] = k[i];
+                        i += 1;
+                    }
+
+                    // The buffer holds the words in little-endian byte order.
+                    let words = words_from_le_bytes(&block);
+                    let mut i = 0;
+                    while i < 16 {
+                        self.m[i] = words[i].to_le();
+                        i += 1;
+                    }
+                    self.t = $bytes * 2;
                 }
             }
 
             /// Updates the hashing context with more data.
-            #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation))]
+            #[inline]
             pub fn update(&mut self, data: &[u8]) {
+                let empty = self.t == 0;
+                self.update_buffer(data, empty);
+            }
+
+            /// Updates the hashing context with more data; `empty` tells
+            /// whether the buffer has no block left to compress.
+            #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation))]
+            fn update_buffer(&mut self, data: &[u8], empty: bool) {
                 let mut rest = data;
 
                 let off = (self.t % ($bytes * 2)) as usize;
-                if off != 0 || self.t == 0 {
+                if off != 0 || empty {
                     let len = cmp::min(($bytes * 2) - off, rest.len());
 
                     let part = &rest[..len];
@@ -180,6 +603,24 @@ macro_rules! blake2_impl {
                 }
             }
 
+            /// Hashes a short piece of data, which usually fits within the
+            /// current block, avoiding the loop of `update`.
+            #[allow(dead_code)]
+            #[inline]
+            pub(crate) fn update_short(&mut self, data: &[u8]) {
+                let off = (self.t % ($bytes * 2)) as usize;
+                if data.is_empty() || off + data.len() > $bytes * 2 {
+                    return self.update(data);
+                }
+
+                if off == 0 && self.t != 0 {
+                    self.compress(0, 0);
+                }
+                self.m.as_mut_bytes()[off..off + data.len()].copy_bytes_from(data);
+                self.t = self.t.checked_add(data.len() as u64)
+                    .expect("hash data length overflow");
+            }
+
             #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation))]
             fn finalize_with_flag(&mut self, f1: $word) {
                 let off = (self.t % ($bytes * 2)) as usize;
@@ -194,14 +635,22 @@ macro_rules! blake2_impl {
             #[inline]
             pub fn finalize(mut self) -> $result {
                 self.finalize_with_flag(0);
-                self.into_result()
+                self.result()
             }
 
             #[doc(hidden)]
             #[inline]
             pub fn finalize_last_node(mut self) -> $result {
                 self.finalize_with_flag(!0);
-                self.into_result()
+                self.result()
+            }
+
+            /// Consumes the hashing context and writes the resulting hash
+            /// to `out`, which must have the length of the hash.
+            pub fn finalize_into(mut self, out: &mut [u8]) {
+                assert_eq!(out.len(), self.nn, "output length mismatch");
+                self.finalize_with_flag(0);
+                out.copy_from_slice(self.result_inplace());
             }
 
             #[doc(hidden)]
@@ -217,7 +666,7 @@ macro_rules! blake2_impl {
             }
 
             #[inline]
-            fn into_result(self) -> $result {
+            fn result(&self) -> $result {
                 $result {
                     h: [self.h[0].to_le(), self.h[1].to_le()],
                     nn: self.nn,
@@ -233,6 +682,14 @@ macro_rules! blake2_impl {
                 result
             }
 
+            /// Compresses the buffered block.
+            #[inline(always)]
+            fn compress(&mut self, f0: $word, f1: $word) {
+                $state::compress_block(&mut self.h, &self.m, self.t, f0, f1);
+            }
+        }
+
+        impl $state {
             #[inline(always)]
             fn quarter_round(v: &mut [$vec; 4], rd: u32, rb: u32, m: $vec) {
                 v[0] = v[0].wrapping_add(v[1]).wrapping_add(m.from_le());
@@ -255,32 +712,43 @@ macro_rules! blake2_impl {
                 v[3] = v[3].shuffle_right_3();
             }
 
+            /// Applies one round to the working vector, with the message
+            /// words in the order given by `s`; also used by BLAKE3.
+            #[allow(dead_code)]
+            #[cfg_attr(not(feature = "small"), inline(always))]
+            pub(crate) fn round(v: &mut [$vec; 4], m: &[$word; 16], s: &[usize; 16]) {
+                $state::round_with_rotations(v, m, s, [$R1, $R2, $R3, $R4]);
+            }
+
+            /// Applies one round with the rotations `r`; also used by the
+            /// original BLAKE, whose 64-bit variant has other rotations.
+            #[allow(dead_code)]
             #[inline(always)]
-            fn round(v: &mut [$vec; 4], m: &[$word; 16], s: &[usize; 16]) {
-                $state::quarter_round(v, $R1, $R2, $vec::gather(m,
+            pub(crate) fn round_with_rotations(v: &mut [$vec; 4], m: &[$word; 16],
+                                               s: &[usize; 16], r: [u32; 4]) {
+                $state::quarter_round(v, r[0], r[1], $vec::gather(m,
                                       s[ 0], s[ 2], s[ 4], s[ 6]));
-                $state::quarter_round(v, $R3, $R4, $vec::gather(m,
+                $state::quarter_round(v, r[2], r[3], $vec::gather(m,
                                       s[ 1], s[ 3], s[ 5], s[ 7]));
 
                 $state::shuffle(v);
-                $state::quarter_round(v, $R1, $R2, $vec::gather(m,
+                $state::quarter_round(v, r[0], r[1], $vec::gather(m,
                                       s[ 8], s[10], s[12], s[14]));
-                $state::quarter_round(v, $R3, $R4, $vec::gather(m,
+                $state::quarter_round(v, r[2], r[3], $vec::gather(m,
                                       s[ 9], s[11], s[13], s[15]));
                 $state::unshuffle(v);
             }
 
+            #[cfg(not(feature = "small"))]
             #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation, eq_op))]
-            fn compress(&mut self, f0: $word, f1: $word) {
+            fn compress_block(h: &mut [$vec; 2], m: &[$word; 16], t: u64,
+                              f0: $word, f1: $word) {
                 use $crate::blake2::SIGMA;
 
-                let m = &self.m;
-                let h = &mut self.h;
-
-                let t0 = self.t as $word;
+                let t0 = t as $word;
                 let t1 = match $bytes {
                     64 => 0,
-                    32 => (self.t >> 32) as $word,
+                    32 => (t >> 32) as $word,
                     _  => unreachable!(),
                 };
 
@@ -309,6 +777,413 @@ macro_rules! blake2_impl {
                 h[0] = h[0] ^ (v[0] ^ v[2]);
                 h[1] = h[1] ^ (v[1] ^ v[3]);
             }
+
+            /// Compresses a block with the scalar compression function,
+            /// for a smaller code size.
+            #[cfg(feature = "small")]
+            #[inline(never)]
+            fn compress_block(h: &mut [$vec; 2], m: &[$word; 16], t: u64,
+                              f0: $word, f1: $word) {
+                let mut words = [h[0].0, h[0].1, h[0].2, h[0].3,
+                                 h[1].0, h[1].1, h[1].2, h[1].3];
+                let mut native = [0; 16];
+                for (word, &raw) in native.iter_mut().zip(m) {
+                    *word = $word::from_le(raw);
+                }
+                $state::compress_words(&mut words, &native, t, f0, f1);
+                h[0] = $vec::new(words[0], words[1], words[2], words[3]);
+                h[1] = $vec::new(words[4], words[5], words[6], words[7]);
+            }
+
+            /// Compresses a block of native-endian words, with a loop over
+            /// the rounds and a scalar G function.
+            ///
+            /// This can be evaluated in const context; with the `small`
+            /// cargo feature, it is also used at runtime.
+            #[cfg_attr(feature = "small", inline(never))]
+            #[cfg_attr(feature = "cargo-clippy", allow(cast_possible_truncation))]
+            const fn compress_words(h: &mut [$word; 8], m: &[$word; 16], t: u64,
+                                    f0: $word, f1: $word) {
+                use $crate::blake2::SIGMA;
+
+                /// Positions in the working vector of the columns, then
+                /// of the diagonals.
+                const POS: [[usize; 4]; 8] = [
+                    [0, 4,  8, 12], [1, 5,  9, 13], [2, 6, 10, 14], [3, 7, 11, 15],
+                    [0, 5, 10, 15], [1, 6, 11, 12], [2, 7,  8, 13], [3, 4,  9, 14],
+                ];
+                const ROUNDS: usize = if $bytes > 32 { 12 } else { 10 };
+
+                let t1 = match $bytes {
+                    64 => 0,
+                    32 => (t >> 32) as $word,
+                    _  => unreachable!(),
+                };
+
+                let mut v = [h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7],
+                             IV[0], IV[1], IV[2], IV[3],
+                             IV[4] ^ t as $word, IV[5] ^ t1, IV[6] ^ f0, IV[7] ^ f1];
+
+                let mut r = 0;
+                while r < ROUNDS {
+                    let s = &SIGMA[r % 10];
+                    let mut i = 0;
+                    while i < 8 {
+                        $state::g(&mut v, &POS[i], m[s[2 * i]], m[s[2 * i + 1]]);
+                        i += 1;
+                    }
+                    r += 1;
+                }
+
+                let mut i = 0;
+                while i < 8 {
+                    h[i] ^= v[i] ^ v[i + 8];
+                    i += 1;
+                }
+            }
+
+            /// The G function, on one column or diagonal of the working
+            /// vector.
+            #[cfg_attr(feature = "small", inline(never))]
+            const fn g(v: &mut [$word; 16], pos: &[usize; 4], x: $word, y: $word) {
+                let [a, b, c, d] = *pos;
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                v[d] = (v[d] ^ v[a]).rotate_right($R1);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($R2);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                v[d] = (v[d] ^ v[a]).rotate_right($R3);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right($R4);
+            }
+        }
+
+        impl Default for $state {
+            fn default() -> Self {
+                Self::new($bytes)
+            }
+        }
+
+        /// Key with its key block already compressed, for keyed hashing
+        /// of many messages.
+        ///
+        /// The hashing contexts made from it have the same result as
+        /// with `with_key`, without compressing the key block again. It
+        /// can be shared between threads. The `Debug` output doesn't
+        /// include the key, and with the `zeroize` cargo feature, it is
+        /// wiped when dropped.
+        #[derive(Clone)]
+        pub struct $key {
+            init: Init,
+        }
+
+        impl $key {
+            /// Precomputes the key block for an `nn`-byte output.
+            pub fn new(nn: usize, k: &[u8]) -> Self {
+                let mut state = $state::with_key(nn, k);
+                let h = state.h;
+                let mut key = None;
+                if !k.is_empty() {
+                    let mut block = [0; $bytes];
+                    block[..k.len()].copy_from_slice(k);
+                    state.compress(0, 0);
+                    key = Some((block, state.h));
+                }
+                $key { init: Init { h, key, nn: nn as u8 } }
+            }
+
+            /// Returns a new hashing context, with the key block already
+            /// compressed.
+            pub fn to_state(&self) -> $resettable {
+                $resettable::from_init(self.init.clone())
+            }
+
+            /// Convenience function for all-in-one computation.
+            pub fn hash(&self, data: &[u8]) -> $result {
+                let mut state = self.to_state();
+                state.update(data);
+                state.finalize()
+            }
+        }
+
+        impl fmt::Debug for $key {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($key))
+                    .field("nn", &self.init.nn)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        /// Hashing context which can be reset to its initial state, for
+        /// hashing many messages with the same key.
+        ///
+        /// Unlike the plain state context, it keeps its initial state,
+        /// with the chaining value after the key block, so resetting it
+        /// doesn't compress the key block again. The `Debug` output
+        /// doesn't include the key, and with the `zeroize` cargo feature,
+        /// it is wiped when dropped.
+        #[derive(Clone)]
+        pub struct $resettable {
+            state: $state,
+            init: Init,
+            /// Whether `state.h` is the chaining value after the key
+            /// block, and no data follows it yet.
+            key_absorbed: bool,
+        }
+
+        impl $resettable {
+            /// Creates a new resettable hashing context without a key.
+            pub fn new(nn: usize) -> Self { Self::with_key(nn, &[]) }
+
+            /// Creates a new resettable hashing context with a key.
+            pub fn with_key(nn: usize, k: &[u8]) -> Self {
+                $key::new(nn, k).to_state()
+            }
+
+            fn from_init(init: Init) -> Self {
+                let mut state = $resettable {
+                    state: $state { m: [0; 16], h: init.h, t: 0, nn: init.nn as usize },
+                    init,
+                    key_absorbed: false,
+                };
+                state.reset();
+                state
+            }
+
+            /// Restores the hashing context to its state just after it
+            /// was created.
+            pub fn reset(&mut self) {
+                self.state.nn = self.init.nn as usize;
+                if let Some((_, h)) = self.init.key {
+                    self.state.h = h;
+                    self.state.t = $bytes * 2;
+                    self.key_absorbed = true;
+                } else {
+                    self.state.h = self.init.h;
+                    self.state.m = [0; 16];
+                    self.state.t = 0;
+                    self.key_absorbed = false;
+                }
+            }
+
+            /// Updates the hashing context with more data.
+            #[inline]
+            pub fn update(&mut self, data: &[u8]) {
+                if self.key_absorbed && !data.is_empty() {
+                    self.key_absorbed = false;
+                    self.state.update_buffer(data, true);
+                } else {
+                    self.state.update(data);
+                }
+            }
+
+            fn finalize_with_flag(&mut self, f1: $word) {
+                if self.key_absorbed {
+                    // The key block is the last block, so it's compressed
+                    // again from the chaining value before it.
+                    if let Some((ref key, _)) = self.init.key {
+                        self.state.m = [0; 16];
+                        self.state.m.as_mut_bytes().copy_bytes_from(key);
+                        self.state.h = self.init.h;
+                    }
+                    self.key_absorbed = false;
+                }
+                self.state.finalize_with_flag(f1);
+            }
+
+            /// Consumes the hashing context and returns the resulting hash.
+            #[inline]
+            pub fn finalize(mut self) -> $result {
+                self.finalize_with_flag(0);
+                self.state.result()
+            }
+
+            #[doc(hidden)]
+            #[inline]
+            pub fn finalize_last_node(mut self) -> $result {
+                self.finalize_with_flag(!0);
+                self.state.result()
+            }
+
+            /// Returns the resulting hash, and resets the hashing context
+            /// to its initial state.
+            pub fn finalize_reset(&mut self) -> $result {
+                self.finalize_with_flag(0);
+                let result = self.state.result();
+                self.reset();
+                result
+            }
+
+            /// Consumes the hashing context and writes the resulting hash
+            /// to `out`, which must have the length of the hash.
+            pub fn finalize_into(mut self, out: &mut [u8]) {
+                assert_eq!(out.len(), self.state.nn, "output length mismatch");
+                self.finalize_with_flag(0);
+                out.copy_from_slice(self.state.result_inplace());
+            }
+
+            #[doc(hidden)]
+            pub fn finalize_inplace(&mut self) -> &[u8] {
+                self.finalize_with_flag(0);
+                self.state.result_inplace()
+            }
+
+            #[doc(hidden)]
+            pub fn finalize_last_node_inplace(&mut self) -> &[u8] {
+                self.finalize_with_flag(!0);
+                self.state.result_inplace()
+            }
+        }
+
+        impl fmt::Debug for $resettable {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($resettable))
+                    .field("t", &self.state.t)
+                    .field("nn", &self.state.nn)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl io::Write for $state {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.t.checked_add(buf.len() as u64).is_none() {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero,
+                                              "counter overflow"));
+                }
+
+                self.update(buf);
+                Ok(buf.len())
+            }
+
+            #[inline]
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    }
+}
+
+macro_rules! blake2p_impl {
+    ($state:ident, $func:ident, $inner:ident, $result:ident,
+     $degree:expr, $bytes:expr) => {
+        use core::cmp;
+
+        #[cfg(feature = "std")]
+        use std::io;
+
+        const BLOCK: usize = $bytes * 2;
+
+        /// State context.
+        #[derive(Clone, Debug)]
+        pub struct $state {
+            leaves: [$inner; $degree],
+            root: $inner,
+            t: u64,
+        }
+
+        fn params(nn: usize, kk: usize) -> Params {
+            Params::new(nn)
+                .key_length(kk)
+                .fanout($degree)
+                .max_depth(2)
+                .inner_length($bytes)
+        }
+
+        fn leaf(nn: usize, k: &[u8], offset: usize) -> $inner {
+            params(nn, k.len())
+                .node_offset(offset as u64)
+                .to_state_with_output(k, $bytes)
+        }
+
+        fn finalize_leaf(leaf: $inner, offset: usize) -> $result {
+            if offset == $degree - 1 {
+                leaf.finalize_last_node()
+            } else {
+                leaf.finalize()
+            }
+        }
+
+        fn root(nn: usize, kk: usize) -> $inner {
+            $inner::with_parameter_block(&params(nn, kk).node_depth(1).to_words())
+        }
+
+        /// Convenience function for all-in-one computation.
+        pub fn $func(nn: usize, k: &[u8], data: &[u8]) -> $result {
+            let mut state = $state::with_key(nn, k);
+            state.update(data);
+            state.finalize()
+        }
+
+        /// Convenience function for all-in-one computation, hashing the
+        /// leaves in parallel.
+        ///
+        /// The result is the same as with the sequential computation.
+        #[cfg(feature = "rayon")]
+        pub fn par_hash(nn: usize, k: &[u8], data: &[u8]) -> $result {
+            let mut hashes = [[0; $bytes]; $degree];
+            ::rayon::scope(|s| {
+                for (offset, hash) in hashes.iter_mut().enumerate() {
+                    s.spawn(move |_| {
+                        let mut state = leaf(nn, k, offset);
+                        for block in data.chunks(BLOCK).skip(offset)
+                                         .step_by($degree) {
+                            state.update(block);
+                        }
+                        hash.copy_from_slice(
+                            finalize_leaf(state, offset).as_bytes());
+                    });
+                }
+            });
+
+            let mut root = root(nn, k.len());
+            for hash in &hashes {
+                root.update(hash);
+            }
+            root.finalize_last_node()
+        }
+
+        impl $state {
+            /// Creates a new hashing context without a key.
+            pub fn new(nn: usize) -> Self { Self::with_key(nn, &[]) }
+
+            /// Creates a new hashing context with a key.
+            pub fn with_key(nn: usize, k: &[u8]) -> Self {
+                assert!((1..=$bytes).contains(&nn) && k.len() <= $bytes);
+
+                $state {
+                    leaves: ::core::array::from_fn(|i| leaf(nn, k, i)),
+                    root: root(nn, k.len()),
+                    t: 0,
+                }
+            }
+
+            /// Updates the hashing context with more data.
+            ///
+            /// Each block of the data goes to the next leaf, in turn.
+            pub fn update(&mut self, data: &[u8]) {
+                let mut rest = data;
+                while !rest.is_empty() {
+                    let off = (self.t % BLOCK as u64) as usize;
+                    let leaf = ((self.t / BLOCK as u64) % $degree) as usize;
+                    let len = cmp::min(BLOCK - off, rest.len());
+
+                    self.leaves[leaf].update(&rest[..len]);
+                    rest = &rest[len..];
+                    self.t = self.t.checked_add(len as u64)
+                        .expect("hash data length overflow");
+                }
+            }
+
+            /// Consumes the hashing context and returns the resulting hash.
+            pub fn finalize(self) -> $result {
+                let mut root = self.root;
+                for (offset, leaf) in IntoIterator::into_iter(self.leaves)
+                                          .enumerate() {
+                    root.update(finalize_leaf(leaf, offset).as_bytes());
+                }
+                root.finalize_last_node()
+            }
         }
 
         impl Default for $state {
@@ -0,0 +1,98 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The original BLAKE-256 and BLAKE-224 hash functions.
+//!
+//! These are the 14-round BLAKE-256 and BLAKE-224 from the final round of
+//! the SHA-3 competition, as used by some older protocols. New designs
+//! should use BLAKE2s instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake256::{Blake256, blake256};
+//!
+//! // Using the convenience function.
+//! let hash = blake256(32, b"The quick brown fox jumps over the lazy dog");
+//!
+//! // Using the state context.
+//! let mut context = Blake256::new(32);
+//! context.update(b"The quick brown fox jumps over the lazy dog");
+//! let hash = context.finalize();
+//!
+//! // BLAKE-224.
+//! let hash = blake256(28, b"The quick brown fox jumps over the lazy dog");
+//! ```
+
+use blake2s::Blake2s;
+
+blake_impl!(Blake256, Blake256Result, blake256, Blake2s, u32, u32x4, u64,
+            32, 28, 14, 16, 12, 8, 7, [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+], [
+    0xC1059ED8, 0x367CD507, 0x3070DD17, 0xF70E5939,
+    0xFFC00B31, 0x68581511, 0x64F98FA7, 0xBEFA4FA4,
+], [
+    0x243F6A88, 0x85A308D3, 0x13198A2E, 0x03707344,
+    0xA4093822, 0x299F31D0, 0x082EFA98, 0xEC4E6C89,
+    0x452821E6, 0x38D01377, 0xBE5466CF, 0x34E90C6C,
+    0xC0AC29B7, 0xC97C50DD, 0x3F84D5B5, 0xB5470917,
+]);
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use super::{Blake256, blake256};
+
+    #[test]
+    fn test_official() {
+        assert_eq!(&blake256(32, &[0]), &HEXUPPER.decode(
+            b"0CE8D4EF4DD7CD8D62DFDED9D4EDB0A774AE6A41929A74DA23109E8F11139C87")
+            .unwrap()[..]);
+        assert_eq!(&blake256(32, &[0; 72]), &HEXUPPER.decode(
+            b"D419BAD32D504FB7D44D460C42C5593FE544FA4C135DEC31E21BD9ABDCC22D41")
+            .unwrap()[..]);
+        assert_eq!(&blake256(28, &[0]), &HEXUPPER.decode(
+            b"4504CB0314FB2A4F7A692E696E487912FE3F2468FE312C73A5278EC5")
+            .unwrap()[..]);
+        assert_eq!(&blake256(28, &[0; 72]), &HEXUPPER.decode(
+            b"F5AA00DD1CB847E3140372AF7B5C46B4888D82C8C0A917913CFB5D04")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(&Blake256::default().finalize(), &HEXUPPER.decode(
+            b"716F6E863F744B9AC22C97EC7B76EA5F5908BC5B2F67C61510BFC4751384EA7A")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_fox() {
+        assert_eq!(&blake256(32, b"The quick brown fox jumps over the lazy dog"),
+                   &HEXUPPER.decode(
+            b"7576698EE9CAD30173080678E5965916ADBB11CB5245D386BF1FFDA1CB26C9D7")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_split() {
+        let data = selftest_seq(512);
+
+        for &len in &[55, 56, 63, 64, 65, 119, 120, 512] {
+            let mut ctx = Blake256::new(32);
+            ctx.update(&data[..3]);
+            ctx.update(&data[3..len / 2]);
+            ctx.update(&data[len / 2..len]);
+            assert_eq!(&ctx.finalize(), &blake256(32, &data[..len]));
+        }
+    }
+}
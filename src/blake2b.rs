@@ -33,16 +33,38 @@
 //! The returned hash is a `Blake2bResult`, which can be compared with
 //! a byte string (the comparison will take constant time), or converted
 //! into a byte string.
+//!
+//! `Blake2bKey` compresses the key block once, for keyed hashing of many
+//! messages with the same key.
+//! `Blake2bResettable` is a hashing context which `reset` and
+//! `finalize_reset` restore to its initial state, for instance to
+//! compute the MAC of each packet; `Blake2bKey::to_state` returns one.
+//!
+//! `blake2b_const` computes a hash in const context, for digests known
+//! at compile time, and `Blake2b::new` and `Blake2b::with_key` can also be
+//! used in const context.
+//!
+//! `TupleHash` and `tuple_hash` hash a sequence of byte strings in an
+//! unambiguous way, with a customization string.
+//!
+//! With the `std` feature, `HashingReader` and `HashingWriter` wrap a
+//! reader or a writer, and hash the data as it passes through them.
 
 #![cfg_attr(feature = "cargo-clippy", allow(unreadable_literal))]
 
-blake2_impl!(Blake2b, Blake2bResult, blake2b, u64, u64x4, 64, 32, 24, 16, 63, [
+blake2_impl!(Blake2b, Blake2bResult, Blake2bKey, Blake2bResettable,
+             blake2b, blake2b_const, u64, u64x4, 64, 32, 24, 16, 63, [
     0x6A09E667F3BCC908, 0xBB67AE8584CAA73B,
     0x3C6EF372FE94F82B, 0xA54FF53A5F1D36F1,
     0x510E527FADE682D1, 0x9B05688C2B3E6C1F,
     0x1F83D9ABFB41BD6B, 0x5BE0CD19137E2179,
 ]);
 
+blake2_tuple_impl!(Blake2b, Blake2bResult);
+
+#[cfg(feature = "std")]
+blake2_tee_impl!(Blake2b, Blake2bResult);
+
 blake2_selftest_impl!(Blake2b, blake2b, [
     0xC2, 0x3A, 0x78, 0x00, 0xD9, 0x81, 0x23, 0xBD,
     0x10, 0xF5, 0x06, 0xC6, 0x1E, 0x29, 0xDA, 0x56,
@@ -58,7 +80,7 @@ mod tests {
     use self::data_encoding::HEXUPPER;
 
     use blake2::selftest_seq;
-    use super::{Blake2b, blake2b};
+    use super::{Blake2b, Blake2bKey, Blake2bResettable, blake2b};
 
     #[test]
     fn test_empty() {
@@ -92,6 +114,118 @@ mod tests {
         assert_eq!(&ctx.finalize(), &blake2b(64, &[], &data));
     }
 
+    #[test]
+    fn test_split_blocks() {
+        let data = selftest_seq(768);
+        let expected = blake2b(64, &[], &data);
+
+        for &i in &[0, 1, 127, 128, 129, 256, 257] {
+            for &j in &[0, 1, 128, 255, 256, 384] {
+                let mut ctx = Blake2b::new(64);
+                ctx.update(&data[..i]);
+                ctx.update(&data[i..i + j]);
+                ctx.update(&data[i + j..]);
+                assert_eq!(ctx.finalize(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Blake2bKey>();
+
+        let data = selftest_seq(512);
+        for &key in &[&b""[..], &b"key"[..], &data[..64]] {
+            let precomputed = Blake2bKey::new(48, key);
+            for &len in &[0, 1, 128, 129, 256, 512] {
+                assert_eq!(precomputed.hash(&data[..len]), blake2b(48, key, &data[..len]));
+
+                let mut state = precomputed.to_state();
+                state.update(&data[..len / 2]);
+                state.update(&data[len / 2..len]);
+                assert_eq!(state.finalize(), blake2b(48, key, &data[..len]));
+
+                let mut state = precomputed.to_state();
+                let mut expected = Blake2b::with_key(48, key);
+                state.update(&data[..len]);
+                expected.update(&data[..len]);
+                assert_eq!(state.finalize_last_node(), expected.finalize_last_node());
+            }
+        }
+    }
+
+    #[test]
+    fn test_const() {
+        use super::blake2b_const;
+
+        const EMPTY: [u8; 64] = blake2b_const::<64>(&[], b"");
+        const KEYED: [u8; 16] = blake2b_const::<16>(b"key", b"abc");
+        const STATE: Blake2b = Blake2b::with_key(64, b"key");
+
+        assert_eq!(&blake2b(64, &[], b""), &EMPTY[..]);
+        assert_eq!(&blake2b(16, b"key", b"abc"), &KEYED[..]);
+
+        let mut ctx = STATE;
+        ctx.update(b"abc");
+        assert_eq!(ctx.finalize(), blake2b(64, b"key", b"abc"));
+
+        let data = selftest_seq(512);
+        for &key in &[&b""[..], &b"key"[..], &data[..64]] {
+            for &len in &[0, 1, 128, 129, 256, 512] {
+                assert_eq!(&blake2b(64, key, &data[..len]),
+                           &blake2b_const::<64>(key, &data[..len])[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let data = selftest_seq(512);
+
+        for &key in &[&b""[..], &b"key"[..]] {
+            let mut ctx = Blake2bResettable::with_key(32, key);
+            assert_eq!(ctx.finalize_reset(), blake2b(32, key, b""));
+            for &len in &[0, 3, 128, 129, 512, 0] {
+                ctx.update(&data[..len]);
+                assert_eq!(ctx.finalize_reset(), blake2b(32, key, &data[..len]));
+            }
+
+            ctx.update(&data[..300]);
+            ctx.reset();
+            ctx.update(b"abc");
+            let mut out = [0; 32];
+            ctx.finalize_into(&mut out);
+            assert_eq!(&blake2b(32, key, b"abc"), &out[..]);
+
+            let mut ctx = Blake2b::with_key(32, key);
+            ctx.update(b"abc");
+            ctx.finalize_into(&mut out);
+            assert_eq!(&blake2b(32, key, b"abc"), &out[..]);
+        }
+    }
+
+    #[test]
+    fn test_reset_last_node() {
+        let data = selftest_seq(512);
+
+        for &key in &[&b""[..], &b"key"[..]] {
+            let mut ctx = Blake2bResettable::with_key(32, key);
+            ctx.update(&data[..100]);
+            ctx.finalize_reset();
+            for &len in &[0, 3, 128, 129, 512] {
+                let mut expected = Blake2b::with_key(32, key);
+                expected.update(&data[..len]);
+                let expected = expected.finalize_last_node();
+
+                ctx.update(&data[..len]);
+                assert_eq!(ctx.clone().finalize_last_node(), expected);
+                assert_eq!(ctx.finalize_last_node_inplace(), expected.as_bytes());
+                ctx.reset();
+            }
+        }
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_write() {
@@ -108,6 +242,82 @@ mod tests {
         assert_eq!(&writer.finalize(), &ctx.finalize());
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hashing_reader() {
+        use std::io::prelude::*;
+        use std::io::ErrorKind;
+        use std::vec::Vec;
+        use super::HashingReader;
+
+        let data = selftest_seq(1024);
+        let hash = blake2b(64, b"key", &data);
+
+        let mut reader = HashingReader::new(&data[..], Blake2b::with_key(64, b"key"));
+        let mut output = [0; 100];
+        reader.read_exact(&mut output).unwrap();
+        let (rest, result) = reader.into_inner_and_result();
+        assert_eq!(rest.len(), 1024 - 100);
+        assert_eq!(result, blake2b(64, b"key", &data[..100]));
+
+        let mut reader = HashingReader::verifying(&data[..], Blake2b::with_key(64, b"key"),
+                                                  &hash);
+        reader.read_to_end(&mut Vec::new()).unwrap();
+
+        let mut reader = HashingReader::verifying(&data[..], Blake2b::new(64),
+                                                  &hash);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut reader = HashingReader::verifying(&data[..], Blake2b::with_key(64, b"key"),
+                                                  &blake2b(32, b"key", &data));
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hashing_writer() {
+        use std::io::prelude::*;
+        use std::io::ErrorKind;
+        use std::vec::Vec;
+        use super::HashingWriter;
+
+        let data = selftest_seq(1024);
+        let hash = blake2b(64, &[], &data);
+
+        let mut writer = HashingWriter::new(Vec::new(), Blake2b::new(64));
+        writer.write_all(&data[..]).unwrap();
+        let (output, result) = writer.into_inner_and_result();
+        assert_eq!(&output[..], &data[..]);
+        assert_eq!(result, hash);
+
+        let mut writer = HashingWriter::verifying(Vec::new(), Blake2b::new(64),
+                                                  &hash);
+        writer.write_all(&data[..]).unwrap();
+        assert_eq!(&writer.finish().unwrap()[..], &data[..]);
+
+        let mut writer = HashingWriter::verifying(Vec::new(), Blake2b::new(64),
+                                                  &hash);
+        writer.write_all(&data[..1023]).unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tuple_hash() {
+        use super::{TupleHash, tuple_hash};
+
+        assert_eq!(&tuple_hash(64, b"My Tuple App", &[b"ab", b"c"]), &HEXUPPER.decode(
+            b"36B1E061FD43984632E4EE634F789AB35B2053338FD7CE9ABAF35B234505E7D6CD11D3B8092EF961E98B1E1DDB55E683BD939C13D49BA7C0AE255A4ABC7BECF3")
+            .unwrap()[..]);
+        assert_eq!(&tuple_hash(64, b"My Tuple App", &[b"a", b"bc"]), &HEXUPPER.decode(
+            b"841D56182E327B767AEC378BC20B2AFA98AF3507C5E25BEC3692219E83D3AACC5F1BF3FFF0A5434AE23A6A3AC4311F485CAF05C6C63E280CBD909C60CFF7E7C8")
+            .unwrap()[..]);
+        assert_eq!(&TupleHash::new(32, &[]).finish(), &HEXUPPER.decode(
+            b"398177DDF46D74AE39AB3DA7ED5A4C9764535939F5FF161C8E6B0B4ECE4ECDF2")
+            .unwrap()[..]);
+    }
+
     #[cfg_attr(debug_assertions, ignore)]
     #[test]
     fn test_4g() {
@@ -121,4 +331,87 @@ mod tests {
             b"645572CA5756F9104329ED543735FC11904F0C18C4DF8ADF930F22D07F3094919A519FF34FD240AE3F5D5B4C8042225C109FB951036FDC99E7D2CD0C1D36B267")
             .unwrap()[..]);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_encodings() {
+        use core::convert::TryFrom;
+        use std::string::ToString;
+        use encoding::Error;
+        use super::Blake2bResult;
+
+        let hash = blake2b(32, &[], b"abc");
+        let hex = "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319";
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(format!("{:x}", hash), hex);
+        assert_eq!(format!("{:X}", hash), hex.to_uppercase());
+        assert_eq!(hex.parse::<Blake2bResult>(), Ok(hash));
+        assert_eq!(hex.to_uppercase().parse::<Blake2bResult>(), Ok(hash));
+
+        let base64 = "vd2BPGNCOXIxce8/7phXm5SWTjuxyz5CcmLIwGjVIxk=";
+        assert_eq!(hash.base64().to_string(), base64);
+        assert_eq!(Blake2bResult::from_base64(base64), Ok(hash));
+
+        let base32 = "XXOYCPDDII4XEMLR54765GCXTOKJMTR3WHFT4QTSMLEMA2GVEMMQ====";
+        assert_eq!(hash.base32().to_string(), base32);
+        assert_eq!(Blake2bResult::from_base32(base32), Ok(hash));
+
+        assert_eq!(Blake2bResult::try_from(hash.as_bytes()), Ok(hash));
+        assert_eq!(Blake2bResult::try_from(&[][..]), Err(Error::InvalidLength));
+        assert_eq!(Blake2bResult::try_from(&[0; 65][..]), Err(Error::InvalidLength));
+        assert_eq!("".parse::<Blake2bResult>(), Err(Error::InvalidLength));
+        assert_eq!(hex[1..].parse::<Blake2bResult>(), Err(Error::InvalidLength));
+        assert_eq!("00".repeat(65).parse::<Blake2bResult>(), Err(Error::InvalidLength));
+        assert_eq!(hex.replace('b', "x").parse::<Blake2bResult>(),
+                   Err(Error::InvalidCharacter));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_debug_redacted() {
+        let state = Blake2b::with_key(64, &[0xAA; 64]);
+        assert_eq!(format!("{:?}", state), "Blake2b { t: 128, nn: 64, .. }");
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_on_drop() {
+        use core::mem::MaybeUninit;
+        use core::ptr;
+        use zeroize::{Zeroize, Zeroizing};
+        use as_bytes::AsBytes;
+
+        let mut state = MaybeUninit::new(Blake2b::with_key(64, &[0xAA; 64]));
+        unsafe {
+            state.assume_init_mut().update(b"data");
+            ptr::drop_in_place(state.as_mut_ptr());
+            let state = &*state.as_ptr();
+            assert!(state.m.iter().all(|&w| w == 0));
+            assert!(state.h.as_bytes().iter().all(|&b| b == 0));
+        }
+
+        let mut state = MaybeUninit::new(Blake2bResettable::with_key(64, &[0xAA; 64]));
+        unsafe {
+            state.assume_init_mut().update(b"data");
+            ptr::drop_in_place(state.as_mut_ptr());
+            let state = &*state.as_ptr();
+            assert!(state.state.m.iter().all(|&w| w == 0));
+            assert!(state.state.h.as_bytes().iter().all(|&b| b == 0));
+            assert!(state.init.h.as_bytes().iter().all(|&b| b == 0));
+            let (ref key, ref h) = *state.init.key.as_ref().unwrap();
+            assert!(key.iter().all(|&b| b == 0));
+            assert!(h.as_bytes().iter().all(|&b| b == 0));
+        }
+
+        let mut result = blake2b(64, &[0xAA; 64], b"data");
+        result.zeroize();
+        assert!(result.h.as_bytes().iter().all(|&b| b == 0));
+
+        let mut result = MaybeUninit::new(Zeroizing::new(blake2b(64, &[0xAA; 64], b"data")));
+        unsafe {
+            ptr::drop_in_place(result.as_mut_ptr());
+            let result = &*result.as_ptr();
+            assert!(result.h.as_bytes().iter().all(|&b| b == 0));
+        }
+    }
 }
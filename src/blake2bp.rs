@@ -0,0 +1,97 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The BLAKE2bp hash function.
+//!
+//! BLAKE2bp hashes the input in 4 parallel BLAKE2b leaves, each one
+//! taking every 4th block of the input, and then hashes the leaf
+//! digests in a BLAKE2b root node. Its result differs from BLAKE2b.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2bp::{Blake2bp, blake2bp};
+//!
+//! // Using the convenience function.
+//! let hash = blake2bp(64, &[], b"The quick brown fox jumps over the lazy dog");
+//!
+//! // Using the state context.
+//! let mut context = Blake2bp::new(64);
+//! context.update(b"The quick brown fox jumps over the lazy dog");
+//! let hash = context.finalize();
+//! ```
+//!
+//! With the `rayon` feature, `par_hash` computes the same result with
+//! the leaves hashed in parallel.
+
+use blake2b::{Blake2b, Blake2bResult, Params};
+
+blake2p_impl!(Blake2bp, blake2bp, Blake2b, Blake2bResult, 4, 64);
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use super::{Blake2bp, blake2bp};
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(&blake2bp(64, &[], b""), &HEXUPPER.decode(
+            b"B5EF811A8038F70B628FA8B294DAAE7492B1EBE343A80EAABBF1F6AE664DD67B9D90B0120791EAB81DC96985F28849F6A305186A85501B405114BFA678DF9380")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_keyed() {
+        let mut key = [0; 64];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        assert_eq!(&blake2bp(64, &key, b""), &HEXUPPER.decode(
+            b"9D9461073E4EB640A255357B839F394B838C6FF57C9B686A3F76107C1066728F3C9956BD785CBC3BF79DC2AB578C5A0C063B9D9C405848DE1DBE821CD05C940A")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut data = [0; 1000];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        assert_eq!(&blake2bp(64, &[], &data), &HEXUPPER.decode(
+            b"440C4C3A7A50159B43A3B80E63083FA88B7E644490061CE763E92426D1FA9F034D0A3A4F94D99042B98D068DA35C5AF694EA9E7F51B8551AF5C99C2EEF95024D")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_split() {
+        let data = selftest_seq(1024);
+
+        let mut ctx = Blake2bp::new(64);
+        ctx.update(&data[..1]);
+        ctx.update(&data[1..128]);
+        ctx.update(&data[128..128 * 3 + 7]);
+        ctx.update(&data[128 * 3 + 7..]);
+
+        assert_eq!(&ctx.finalize(), &blake2bp(64, &[], &data));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_hash() {
+        let data = selftest_seq(1024);
+        let key = selftest_seq(64);
+        for &len in &[0, 1, 128, 128 * 4 + 1, 1024] {
+            assert_eq!(super::par_hash(64, &key, &data[..len]),
+                       blake2bp(64, &key, &data[..len]));
+            assert_eq!(super::par_hash(20, &[], &data[..len]),
+                       blake2bp(20, &[], &data[..len]));
+        }
+    }
+}
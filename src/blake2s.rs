@@ -33,14 +33,36 @@
 //! The returned hash is a `Blake2sResult`, which can be compared with
 //! a byte string (the comparison will take constant time), or converted
 //! into a byte string.
+//!
+//! `Blake2sKey` compresses the key block once, for keyed hashing of many
+//! messages with the same key.
+//! `Blake2sResettable` is a hashing context which `reset` and
+//! `finalize_reset` restore to its initial state, for instance to
+//! compute the MAC of each packet; `Blake2sKey::to_state` returns one.
+//!
+//! `blake2s_const` computes a hash in const context, for digests known
+//! at compile time, and `Blake2s::new` and `Blake2s::with_key` can also be
+//! used in const context.
+//!
+//! `TupleHash` and `tuple_hash` hash a sequence of byte strings in an
+//! unambiguous way, with a customization string.
+//!
+//! With the `std` feature, `HashingReader` and `HashingWriter` wrap a
+//! reader or a writer, and hash the data as it passes through them.
 
 #![cfg_attr(feature = "cargo-clippy", allow(unreadable_literal))]
 
-blake2_impl!(Blake2s, Blake2sResult, blake2s, u32, u32x4, 32, 16, 12, 8, 7, [
+blake2_impl!(Blake2s, Blake2sResult, Blake2sKey, Blake2sResettable,
+             blake2s, blake2s_const, u32, u32x4, 32, 16, 12, 8, 7, [
     0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
     0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
 ]);
 
+blake2_tuple_impl!(Blake2s, Blake2sResult);
+
+#[cfg(feature = "std")]
+blake2_tee_impl!(Blake2s, Blake2sResult);
+
 blake2_selftest_impl!(Blake2s, blake2s, [
     0x6A, 0x41, 0x1F, 0x08, 0xCE, 0x25, 0xAD, 0xCD,
     0xFB, 0x02, 0xAB, 0xA6, 0x41, 0x45, 0x1C, 0xEC,
@@ -56,7 +78,7 @@ mod tests {
     use self::data_encoding::HEXUPPER;
 
     use blake2::selftest_seq;
-    use super::{Blake2s, blake2s};
+    use super::{Blake2s, Blake2sKey, Blake2sResettable, blake2s};
 
     #[test]
     fn test_empty() {
@@ -90,6 +112,127 @@ mod tests {
         assert_eq!(&ctx.finalize(), &blake2s(32, &[], &data));
     }
 
+    #[test]
+    fn test_split_blocks() {
+        let data = selftest_seq(384);
+        let expected = blake2s(32, &[], &data);
+
+        for &i in &[0, 1, 63, 64, 65, 128, 129] {
+            for &j in &[0, 1, 64, 127, 128, 192] {
+                let mut ctx = Blake2s::new(32);
+                ctx.update(&data[..i]);
+                ctx.update(&data[i..i + j]);
+                ctx.update(&data[i + j..]);
+                assert_eq!(ctx.finalize(), expected);
+            }
+        }
+
+        // Whole blocks at every alignment of the input.
+        for offset in 1..4 {
+            let mut ctx = Blake2s::new(32);
+            for byte in data[offset..].chunks(1) {
+                ctx.update(byte);
+            }
+            assert_eq!(ctx.finalize(), blake2s(32, &[], &data[offset..]));
+        }
+    }
+
+    #[test]
+    fn test_const() {
+        use super::blake2s_const;
+
+        const EMPTY: [u8; 32] = blake2s_const::<32>(&[], b"");
+        const KEYED: [u8; 16] = blake2s_const::<16>(b"key", b"abc");
+        const STATE: Blake2s = Blake2s::with_key(32, b"key");
+
+        assert_eq!(&blake2s(32, &[], b""), &EMPTY[..]);
+        assert_eq!(&blake2s(16, b"key", b"abc"), &KEYED[..]);
+
+        let mut ctx = STATE;
+        ctx.update(b"abc");
+        assert_eq!(ctx.finalize(), blake2s(32, b"key", b"abc"));
+
+        let data = selftest_seq(256);
+        for &key in &[&b""[..], &b"key"[..], &data[..32]] {
+            for &len in &[0, 1, 64, 65, 128, 256] {
+                assert_eq!(&blake2s(32, key, &data[..len]),
+                           &blake2s_const::<32>(key, &data[..len])[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Blake2sKey>();
+
+        let data = selftest_seq(256);
+        for &key in &[&b""[..], &b"key"[..], &data[..32]] {
+            let precomputed = Blake2sKey::new(24, key);
+            for &len in &[0, 1, 64, 65, 128, 256] {
+                assert_eq!(precomputed.hash(&data[..len]), blake2s(24, key, &data[..len]));
+
+                let mut state = precomputed.to_state();
+                state.update(&data[..len / 2]);
+                state.update(&data[len / 2..len]);
+                assert_eq!(state.finalize(), blake2s(24, key, &data[..len]));
+
+                let mut state = precomputed.to_state();
+                let mut expected = Blake2s::with_key(24, key);
+                state.update(&data[..len]);
+                expected.update(&data[..len]);
+                assert_eq!(state.finalize_last_node(), expected.finalize_last_node());
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let data = selftest_seq(256);
+
+        for &key in &[&b""[..], &b"key"[..]] {
+            let mut ctx = Blake2sResettable::with_key(32, key);
+            assert_eq!(ctx.finalize_reset(), blake2s(32, key, b""));
+            for &len in &[0, 3, 64, 65, 256, 0] {
+                ctx.update(&data[..len]);
+                assert_eq!(ctx.finalize_reset(), blake2s(32, key, &data[..len]));
+            }
+
+            ctx.update(&data[..150]);
+            ctx.reset();
+            ctx.update(b"abc");
+            let mut out = [0; 32];
+            ctx.finalize_into(&mut out);
+            assert_eq!(&blake2s(32, key, b"abc"), &out[..]);
+
+            let mut ctx = Blake2s::with_key(32, key);
+            ctx.update(b"abc");
+            ctx.finalize_into(&mut out);
+            assert_eq!(&blake2s(32, key, b"abc"), &out[..]);
+        }
+    }
+
+    #[test]
+    fn test_reset_last_node() {
+        let data = selftest_seq(256);
+
+        for &key in &[&b""[..], &b"key"[..]] {
+            let mut ctx = Blake2sResettable::with_key(16, key);
+            ctx.update(&data[..50]);
+            ctx.finalize_reset();
+            for &len in &[0, 3, 64, 65, 256] {
+                let mut expected = Blake2s::with_key(16, key);
+                expected.update(&data[..len]);
+                let expected = expected.finalize_last_node();
+
+                ctx.update(&data[..len]);
+                assert_eq!(ctx.clone().finalize_last_node(), expected);
+                assert_eq!(ctx.finalize_last_node_inplace(), expected.as_bytes());
+                ctx.reset();
+            }
+        }
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_write() {
@@ -106,6 +249,77 @@ mod tests {
         assert_eq!(&writer.finalize(), &ctx.finalize());
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hashing_reader() {
+        use std::io::prelude::*;
+        use std::io::ErrorKind;
+        use std::vec::Vec;
+        use super::HashingReader;
+
+        let data = selftest_seq(1024);
+        let hash = blake2s(32, b"key", &data);
+
+        let mut reader = HashingReader::new(&data[..], Blake2s::with_key(32, b"key"));
+        let mut output = [0; 100];
+        reader.read_exact(&mut output).unwrap();
+        let (rest, result) = reader.into_inner_and_result();
+        assert_eq!(rest.len(), 1024 - 100);
+        assert_eq!(result, blake2s(32, b"key", &data[..100]));
+
+        let mut reader = HashingReader::verifying(&data[..], Blake2s::with_key(32, b"key"),
+                                                  &hash);
+        reader.read_to_end(&mut Vec::new()).unwrap();
+
+        let mut reader = HashingReader::verifying(&data[..], Blake2s::new(32),
+                                                  &hash);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hashing_writer() {
+        use std::io::prelude::*;
+        use std::io::ErrorKind;
+        use std::vec::Vec;
+        use super::HashingWriter;
+
+        let data = selftest_seq(1024);
+        let hash = blake2s(32, &[], &data);
+
+        let mut writer = HashingWriter::new(Vec::new(), Blake2s::new(32));
+        writer.write_all(&data[..]).unwrap();
+        let (output, result) = writer.into_inner_and_result();
+        assert_eq!(&output[..], &data[..]);
+        assert_eq!(result, hash);
+
+        let mut writer = HashingWriter::verifying(Vec::new(), Blake2s::new(32),
+                                                  &hash);
+        writer.write_all(&data[..]).unwrap();
+        assert_eq!(&writer.finish().unwrap()[..], &data[..]);
+
+        let mut writer = HashingWriter::verifying(Vec::new(), Blake2s::new(32),
+                                                  &hash);
+        writer.write_all(&data[..1023]).unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tuple_hash() {
+        use super::{TupleHash, tuple_hash};
+
+        assert_eq!(&tuple_hash(32, b"tuple", &[b"ab", b"c", &[0; 300]]), &HEXUPPER.decode(
+            b"1B00EFAA62E249D247BFCA66636D8FD7F78A3F19E2CA891DF23FD6AB6DBD0B00")
+            .unwrap()[..]);
+
+        let mut context = TupleHash::with_key(16, b"key", b"tuple");
+        context.push_field(&[]);
+        assert_eq!(&context.finish(), &HEXUPPER.decode(
+            b"2BD27D50F04D535927CE9A1AAB9F2B6A")
+            .unwrap()[..]);
+    }
+
     #[cfg_attr(debug_assertions, ignore)]
     #[test]
     fn test_4g() {
@@ -0,0 +1,97 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The BLAKE2sp hash function.
+//!
+//! BLAKE2sp hashes the input in 8 parallel BLAKE2s leaves, each one
+//! taking every 8th block of the input, and then hashes the leaf
+//! digests in a BLAKE2s root node. Its result differs from BLAKE2s.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2sp::{Blake2sp, blake2sp};
+//!
+//! // Using the convenience function.
+//! let hash = blake2sp(32, &[], b"The quick brown fox jumps over the lazy dog");
+//!
+//! // Using the state context.
+//! let mut context = Blake2sp::new(32);
+//! context.update(b"The quick brown fox jumps over the lazy dog");
+//! let hash = context.finalize();
+//! ```
+//!
+//! With the `rayon` feature, `par_hash` computes the same result with
+//! the leaves hashed in parallel.
+
+use blake2s::{Blake2s, Blake2sResult, Params};
+
+blake2p_impl!(Blake2sp, blake2sp, Blake2s, Blake2sResult, 8, 32);
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use super::{Blake2sp, blake2sp};
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(&blake2sp(32, &[], b""), &HEXUPPER.decode(
+            b"DD0E891776933F43C7D032B08A917E25741F8AA9A12C12E1CAC8801500F2CA4F")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_keyed() {
+        let mut key = [0; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        assert_eq!(&blake2sp(32, &key, b""), &HEXUPPER.decode(
+            b"715CB13895AEB678F6124160BFF21465B30F4F6874193FC851B4621043F09CC6")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut data = [0; 1000];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        assert_eq!(&blake2sp(32, &[], &data), &HEXUPPER.decode(
+            b"611F1AF6610CDAF674EC2C9178F6376EBE234EF50998A3BE3F1FA698FB779274")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_split() {
+        let data = selftest_seq(1024);
+
+        let mut ctx = Blake2sp::new(32);
+        ctx.update(&data[..1]);
+        ctx.update(&data[1..64]);
+        ctx.update(&data[64..64 * 3 + 7]);
+        ctx.update(&data[64 * 3 + 7..]);
+
+        assert_eq!(&ctx.finalize(), &blake2sp(32, &[], &data));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_hash() {
+        let data = selftest_seq(1024);
+        let key = selftest_seq(32);
+        for &len in &[0, 1, 64, 64 * 8 + 1, 1024] {
+            assert_eq!(super::par_hash(32, &key, &data[..len]),
+                       blake2sp(32, &key, &data[..len]));
+            assert_eq!(super::par_hash(20, &[], &data[..len]),
+                       blake2sp(20, &[], &data[..len]));
+        }
+    }
+}
@@ -0,0 +1,631 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The BLAKE3 hash function.
+//!
+//! The BLAKE3 compression function is 7 rounds of the BLAKE2s round, with
+//! a fixed permutation of the message words between rounds, so this module
+//! uses the same vector code as `Blake2s`. The input is split into
+//! 1024-byte chunks, which are combined with a binary tree; the tree is
+//! built incrementally, so the hashing context has a fixed size and
+//! doesn't need memory allocation.
+//!
+//! Besides the default mode, `keyed_hash` is a MAC with a 32-byte key,
+//! and `derive_key` derives keys from key material and a context string.
+//! Any of the three modes can produce an output of any length with
+//! `finalize_xof`.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake3::{self, Blake3};
+//!
+//! // Using the convenience function.
+//! let hash = blake3::hash(b"The quick brown fox jumps over the lazy dog");
+//!
+//! // Using the state context.
+//! let mut context = Blake3::new();
+//! context.update(b"The quick brown fox jumps over the lazy dog");
+//! assert_eq!(context.finalize(), hash);
+//!
+//! // Extended output.
+//! let mut context = Blake3::new_derive_key("example.com 2026 session keys");
+//! context.update(b"key material");
+//! let mut key = [0; 100];
+//! context.finalize_xof().fill(&mut key);
+//! ```
+
+use core::cmp;
+use core::fmt;
+
+#[cfg(feature = "std")]
+use std::io;
+
+use as_bytes::AsBytes;
+use blake2s::{Blake2s, IV};
+use bytes::BytesExt;
+use constant_time_eq::constant_time_eq;
+use simd::{Vector4, u32x4};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Length of the default output.
+pub const OUT_LEN: usize = 32;
+
+/// Length of the key of `keyed_hash`.
+pub const KEY_LEN: usize = 32;
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+/// Maximum depth of the tree, for 2^64 bytes of input.
+const MAX_DEPTH: usize = 54;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+const KEYED_HASH: u32 = 1 << 4;
+const DERIVE_KEY_CONTEXT: u32 = 1 << 5;
+const DERIVE_KEY_MATERIAL: u32 = 1 << 6;
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// Order of the message words in each round, as `SIGMA` for BLAKE2.
+const MSG_SCHEDULE: [[usize; 16]; 7] = {
+    let mut schedule = [[0; 16]; 7];
+    let mut i = 0;
+    while i < 16 {
+        schedule[0][i] = i;
+        i += 1;
+    }
+    let mut r = 1;
+    while r < 7 {
+        let mut i = 0;
+        while i < 16 {
+            schedule[r][i] = schedule[r - 1][MSG_PERMUTATION[i]];
+            i += 1;
+        }
+        r += 1;
+    }
+    schedule
+};
+
+/// Chaining value, as native-endian words.
+type Cv = [u32; 8];
+
+/// Compresses a block, whose words are in little-endian byte order, and
+/// returns the whole working vector.
+fn compress(cv: &Cv, block: &[u32; 16], counter: u64, block_len: u32,
+            flags: u32) -> [u32x4; 4] {
+    let h = [u32x4::new(cv[0], cv[1], cv[2], cv[3]),
+             u32x4::new(cv[4], cv[5], cv[6], cv[7])];
+    let mut v = [
+        h[0],
+        h[1],
+        u32x4::new(IV[0], IV[1], IV[2], IV[3]),
+        u32x4::new(counter as u32, (counter >> 32) as u32, block_len, flags),
+    ];
+    for s in &MSG_SCHEDULE {
+        Blake2s::round(&mut v, block, s);
+    }
+    [v[0] ^ v[2], v[1] ^ v[3], v[2] ^ h[0], v[3] ^ h[1]]
+}
+
+fn to_cv(v: &[u32x4; 4]) -> Cv {
+    [v[0].0, v[0].1, v[0].2, v[0].3, v[1].0, v[1].1, v[1].2, v[1].3]
+}
+
+fn key_words(key: &[u8; KEY_LEN]) -> Cv {
+    let mut words = [0; 8];
+    words.as_mut_bytes().copy_bytes_from(key);
+    for word in &mut words {
+        *word = u32::from_le(*word);
+    }
+    words
+}
+
+/// Inputs of the last compression of a node, which can produce either a
+/// chaining value or the root output.
+#[derive(Clone)]
+struct Output {
+    input_cv: Cv,
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn parent(left: &Cv, right: &Cv, key: &Cv, flags: u32) -> Self {
+        let mut block = [0; 16];
+        for (word, &cv) in block.iter_mut().zip(left.iter().chain(right)) {
+            *word = cv.to_le();
+        }
+        Output {
+            input_cv: *key,
+            block,
+            counter: 0,
+            block_len: BLOCK_LEN as u32,
+            flags: PARENT | flags,
+        }
+    }
+
+    fn chaining_value(&self) -> Cv {
+        to_cv(&compress(&self.input_cv, &self.block, self.counter,
+                        self.block_len, self.flags))
+    }
+
+    fn root_block(&self, counter: u64) -> [u32x4; 4] {
+        let mut v = compress(&self.input_cv, &self.block, counter,
+                             self.block_len, self.flags | ROOT);
+        for x in &mut v {
+            *x = x.to_le();
+        }
+        v
+    }
+}
+
+#[derive(Clone)]
+struct ChunkState {
+    cv: Cv,
+    chunk_counter: u64,
+    block: [u32; 16],
+    block_len: usize,
+    blocks_compressed: usize,
+    flags: u32,
+}
+
+impl ChunkState {
+    fn new(key: &Cv, chunk_counter: u64, flags: u32) -> Self {
+        ChunkState {
+            cv: *key,
+            chunk_counter,
+            block: [0; 16],
+            block_len: 0,
+            blocks_compressed: 0,
+            flags,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            if self.block_len == BLOCK_LEN {
+                let flags = self.flags | self.start_flag();
+                self.cv = to_cv(&compress(&self.cv, &self.block, self.chunk_counter,
+                                          BLOCK_LEN as u32, flags));
+                self.blocks_compressed += 1;
+                self.block = [0; 16];
+                self.block_len = 0;
+            }
+
+            let len = cmp::min(BLOCK_LEN - self.block_len, input.len());
+            self.block.as_mut_bytes()[self.block_len..].copy_bytes_from(&input[..len]);
+            self.block_len += len;
+            input = &input[len..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        Output {
+            input_cv: self.cv,
+            block: self.block,
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.flags | self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+/// Container for a hash result.
+///
+/// This container uses a constant-time comparison for equality.
+/// If a constant-time comparison is not necessary, the hash
+/// result can be extracted with the `as_bytes` method.
+///
+/// With the `zeroize` cargo feature, it implements `Zeroize`, but as it
+/// is `Copy`, it is not wiped when dropped; to wipe it, wrap it in
+/// `zeroize::Zeroizing`.
+#[derive(Clone, Copy, Debug)]
+pub struct Blake3Result {
+    h: [u8; OUT_LEN],
+}
+
+impl Blake3Result {
+    /// Returns the contained hash result as a byte string.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] { &self.h }
+}
+
+impl AsRef<[u8]> for Blake3Result {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { self.as_bytes() }
+}
+
+impl Eq for Blake3Result { }
+
+impl PartialEq for Blake3Result {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl PartialEq<[u8]> for Blake3Result {
+    #[inline]
+    fn eq(&self, other: &[u8]) -> bool {
+        constant_time_eq(self.as_bytes(), other)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Blake3Result {
+    fn zeroize(&mut self) {
+        self.h.zeroize();
+    }
+}
+
+/// Reader for the extended output of `Blake3::finalize_xof`.
+#[derive(Clone)]
+pub struct Blake3Xof {
+    output: Output,
+    counter: u64,
+    pos: usize,
+}
+
+impl Blake3Xof {
+    /// Fills `buf` with the next bytes of the output.
+    pub fn fill(&mut self, mut buf: &mut [u8]) {
+        while !buf.is_empty() {
+            let block = self.output.root_block(self.counter);
+            let len = cmp::min(BLOCK_LEN - self.pos, buf.len());
+            let (part, rest) = buf.split_at_mut(len);
+            part.copy_from_slice(&block.as_bytes()[self.pos..self.pos + len]);
+            buf = rest;
+
+            self.pos += len;
+            if self.pos == BLOCK_LEN {
+                self.counter += 1;
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Returns the position within the output.
+    pub fn position(&self) -> u64 {
+        self.counter * BLOCK_LEN as u64 + self.pos as u64
+    }
+}
+
+impl fmt::Debug for Blake3Xof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Blake3Xof")
+            .field("position", &self.position())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Blake3Xof {
+    fn drop(&mut self) {
+        self.output.input_cv.zeroize();
+        self.output.block.zeroize();
+    }
+}
+
+/// Convenience function for hashing in the default mode.
+pub fn hash(data: &[u8]) -> Blake3Result {
+    let mut state = Blake3::new();
+    state.update(data);
+    state.finalize()
+}
+
+/// Convenience function for hashing with a key.
+pub fn keyed_hash(key: &[u8; KEY_LEN], data: &[u8]) -> Blake3Result {
+    let mut state = Blake3::new_keyed(key);
+    state.update(data);
+    state.finalize()
+}
+
+/// Convenience function for deriving a 32-byte key.
+///
+/// The context string should be hardcoded, globally unique, and
+/// application-specific.
+pub fn derive_key(context: &str, key_material: &[u8]) -> [u8; OUT_LEN] {
+    let mut state = Blake3::new_derive_key(context);
+    state.update(key_material);
+    state.finalize().h
+}
+
+/// State context.
+///
+/// The `Debug` output doesn't include the key or the buffered data. With
+/// the `zeroize` cargo feature, the state is wiped when dropped.
+#[derive(Clone)]
+pub struct Blake3 {
+    chunk_state: ChunkState,
+    key: Cv,
+    cv_stack: [Cv; MAX_DEPTH],
+    cv_stack_len: usize,
+    flags: u32,
+}
+
+impl Blake3 {
+    fn new_internal(key: Cv, flags: u32) -> Self {
+        Blake3 {
+            chunk_state: ChunkState::new(&key, 0, flags),
+            key,
+            cv_stack: [[0; 8]; MAX_DEPTH],
+            cv_stack_len: 0,
+            flags,
+        }
+    }
+
+    /// Creates a new hashing context for the default mode.
+    pub fn new() -> Self {
+        Self::new_internal(IV, 0)
+    }
+
+    /// Creates a new hashing context with a key.
+    pub fn new_keyed(key: &[u8; KEY_LEN]) -> Self {
+        Self::new_internal(key_words(key), KEYED_HASH)
+    }
+
+    /// Creates a new hashing context for key derivation.
+    pub fn new_derive_key(context: &str) -> Self {
+        let mut state = Self::new_internal(IV, DERIVE_KEY_CONTEXT);
+        state.update(context.as_bytes());
+        let context_key = state.finalize();
+        Self::new_internal(key_words(&context_key.h), DERIVE_KEY_MATERIAL)
+    }
+
+    /// Updates the hashing context with more data.
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_cv(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(&self.key, total_chunks, self.flags);
+            }
+
+            let len = cmp::min(CHUNK_LEN - self.chunk_state.len(), data.len());
+            self.chunk_state.update(&data[..len]);
+            data = &data[len..];
+        }
+    }
+
+    /// Merges the completed subtrees, as many as the number of trailing
+    /// zero bits in the total number of chunks.
+    fn add_chunk_cv(&mut self, mut cv: Cv, mut total_chunks: u64) {
+        while total_chunks & 1 == 0 {
+            self.cv_stack_len -= 1;
+            cv = Output::parent(&self.cv_stack[self.cv_stack_len], &cv,
+                                &self.key, self.flags).chaining_value();
+            total_chunks >>= 1;
+        }
+        self.cv_stack[self.cv_stack_len] = cv;
+        self.cv_stack_len += 1;
+    }
+
+    fn output(&self) -> Output {
+        let mut output = self.chunk_state.output();
+        for left in self.cv_stack[..self.cv_stack_len].iter().rev() {
+            output = Output::parent(left, &output.chaining_value(),
+                                    &self.key, self.flags);
+        }
+        output
+    }
+
+    /// Consumes the hashing context and returns the resulting hash.
+    pub fn finalize(self) -> Blake3Result {
+        let mut h = [0; OUT_LEN];
+        h.copy_from_slice(&self.output().root_block(0).as_bytes()[..OUT_LEN]);
+        Blake3Result { h }
+    }
+
+    /// Consumes the hashing context and returns a reader for an output
+    /// of any length, whose first 32 bytes are the resulting hash.
+    pub fn finalize_xof(self) -> Blake3Xof {
+        Blake3Xof { output: self.output(), counter: 0, pos: 0 }
+    }
+}
+
+impl fmt::Debug for Blake3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Blake3")
+            .field("chunk_counter", &self.chunk_state.chunk_counter)
+            .field("chunk_len", &self.chunk_state.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Blake3 {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.cv_stack.zeroize();
+        self.chunk_state.cv.zeroize();
+        self.chunk_state.block.zeroize();
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Blake3 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use super::{Blake3, derive_key, hash, keyed_hash};
+
+    const KEY: &[u8; 32] = b"whats the Elvish word for friend";
+    const CONTEXT: &str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+    // Cases from the official test vectors: input length, and the
+    // 131-byte extended outputs of hash, keyed_hash and derive_key.
+    const VECTORS: &[(usize, &str, &str, &str)] = &[
+        (0,
+         "AF1349B9F5F9A1A6A0404DEA36DCC9499BCB25C9ADC112B7CC9A93CAE41F3262E00F03E7B69AF26B7FAAF09FCD333050338DDFE085B8CC869CA98B206C08243A26F5487789E8F660AFE6C99EF9E0C52B92E7393024A80459CF91F476F9FFDBDA7001C22E159B402631F277CA96F2DEFDF1078282314E763699A31C5363165421CCE14D",
+         "92B2B75604ED3C761F9D6F62392C8A9227AD0EA3F09573E783F1498A4ED60D26B18171A2F22A4B94822C701F107153DBA24918C4BAE4D2945C20ECE13387627D3B73CBF97B797D5E59948C7EF788F54372DF45E45E4293C7DC18C1D41144A9758BE58960856BE1EABBE22C2653190DE560CA3B2AC4AA692A9210694254C371E851BC8F",
+         "2CC39783C223154FEA8DFB7C1B1660F2AC2DCBD1C1DE8277B0B0DD39B7E50D7D905630C8BE290DFCF3E6842F13BDDD573C098C3F17361F1F206B8CAD9D088AA4A3F746752C6B0CE6A83B0DA81D59649257CDF8EB3E9F7D4998E41021FAC119DEEFB896224AC99F860011F73609E6E0E4540F93B273E56547DFD3AA1A035BA6689D89A0"),
+        (1,
+         "2D3ADEDFF11B61F14C886E35AFA036736DCD87A74D27B5C1510225D0F592E213C3A6CB8BF623E20CDB535F8D1A5FFB86342D9C0B64ACA3BCE1D31F60ADFA137B358AD4D79F97B47C3D5E79F179DF87A3B9776EF8325F8329886BA42F07FB138BB502F4081CBCEC3195C5871E6C23E2CC97D3C69A613EBA131E5F1351F3F1DA786545E5",
+         "6D7878DFFF2F485635D39013278AE14F1454B8C0A3A2D34BC1AB38228A80C95B6568C0490609413006FBD428EB3FD14E7756D90F73A4725FAD147F7BF70FD61C4E0CF7074885E92B0E3F125978B4154986D4FB202A3F331A3FB6CF349A3A70E49990F98FE4289761C8602C4E6AB1138D31D3B62218078B2F3BA9A88E1D08D0DD4CEA11",
+         "B3E2E340A117A499C6CF2398A19EE0D29CCA2BB7404C73063382693BF66CB06C5827B91BF889B6B97C5477F535361CAEFCA0B5D8C4746441C57617111933158950670F9AA8A05D791DAAE10AC683CBEF8FAF897C84E6114A59D2173C3F417023A35D6983F2C7DFA57E7FC559AD751DBFB9FFAB39C2EF8C4AAFEBC9AE973A64F0C76551"),
+        (64,
+         "4EED7141EA4A5CD4B788606BD23F46E212AF9CACEBACDC7D1F4C6DC7F2511B98FC9CC56CB831FFE33EA8E7E1D1DF09B26EFD2767670066AA82D023B1DFE8AB1B2B7FBB5B97592D46FFE3E05A6A9B592E2949C74160E4674301BC3F97E04903F8C6CF95B863174C33228924CDEF7AE47559B10B294ACD660666C4538833582B43F82D74",
+         "BA8CED36F327700D213F120B1A207A3B8C04330528586F414D09F2F7D9CCB7E68244C26010AFC3F762615BBAC552A1CA909E67C83E2FD5478CF46B9E811EFCCC93F77A21B17A152EBACA1695733FDB086E23CD0EB48C41C034D52523FC21236E5D8C9255306E48D52BA40B4DAC24256460D56573D1312319AFCF3ED39D72D0BFC69ACB",
+         "A5C4A7053FA86B64746D4BB688D06AD1F02A18FCE9AFD3E818FEFAA7126BF73E9B9493A9BEFEBE0BF0C9509FB3105CFA0E262CDE141AA8E3F2C2F77890BB64A4CCA96922A21EAD111F6338AD5244F2C15C44CB595443AC2AC294231E31BE4A4307D0A91E874D36FC9852AEB1265C09B6E0CDA7C37EF686FBBCAB97E8FF66718BE048BB"),
+        (65,
+         "DE1E5FA0BE70DF6D2BE8FFFD0E99CEAA8EB6E8C93A63F2D8D1C30ECB6B263DEE0E16E0A4749D6811DD1D6D1265C29729B1B75A9AC346CF93F0E1D7296DFCFD4313B3A227FAAAAF7757CC95B4E87A49BE3B8A270A12020233509B1C3632B3485EEF309D0ABC4A4A696C9DECC6E90454B53B000F456A3F10079072BAAF7A981653221F2C",
+         "C0A4EDEFA2D2ACCB9277C371AC12FCDBB52988A86EDC54F0716E1591B4326E72D5E795F46A596B02D3D4BFB43ABAD1E5D19211152722EC1F20FEF2CD413E3C22F2FC5DA3D73041275BE6EDE3517B3B9F0FC67ADE5956A672B8B75D96CB43294B9041497DE92637ED3F2439225E683910CB3AE923374449CA788FB0F9BEA92731BC26AD",
+         "51FD05C3C1CFBC8ED67D139AD76F5CF8236CD2ACD26627A30C104DFD9D3FF8A82B02E8BD36D8498A75AD8C8E9B15EB386970283D6DD42C8AE7911CC592887FDBE26A0A5F0BF821CD92986C60B2502C9BE3F98A9C133A7E8045EA867E0828C7252E739321F7C2D65DAEE4468EB4429EFAE469A42763F1F94977435D10DCCAE3E3DCE88D"),
+        (1023,
+         "10108970EEDA3EB932BAAC1428C7A2163B0E924C9A9E25B35BBA72B28F70BD11A182D27A591B05592B15607500E1E8DD56BC6C7FC063715B7A1D737DF5BAD3339C56778957D870EB9717B57EA3D9FB68D1B55127BBA6A906A4A24BBD5ACB2D123A37B28F9E9A81BBAAE360D58F85E5FC9D75F7C370A0CC09B6522D9C8D822F2F28F485",
+         "C951ECDF03288D0FCC96EE3413563D8A6D3589547F2C2FB36D9786470F1B9D6E890316D2E6D8B8C25B0A5B2180F94FB1A158EF508C3CDE45E2966BD796A696D3E13EFD86259D756387D9BECF5C8BF1CE2192B87025152907B6D8CC33D17826D8B7B9BC97E38C3C85108EF09F013E01C229C20A83D9E8EFAC5B37470DA28575FD755A10",
+         "74A16C1C3D44368A86E1CA6DF64BE6A2F64CCE8F09220787450722D85725DEA59C413264404661E9E4D955409DFE4AD3AA487871BCD454ED12ABFE2C2B1EB7757588CF6CB18D2ECCAD49E018C0D0FEC323BEC82BF1644C6325717D13EA712E6840D3E6E730D35553F59EFF5377A9C350BCC1556694B924B858F329C44EE64B884EF00D"),
+        (1024,
+         "42214739F095A406F3FC83DEB889744AC00DF831C10DAA55189B5D121C855AF71CF8107265ECDAF8505B95D8FCEC83A98A6A96EA5109D2C179C47A387FFBB404756F6EEAE7883B446B70EBB144527C2075AB8AB204C0086BB22B7C93D465EFC57F8D917F0B385C6DF265E77003B85102967486ED57DB5C5CA170BA441427ED9AFA684E",
+         "75C46F6F3D9EB4F55ECAAEE480DB732E6C2105546F1E675003687C31719C7BA4A78BC838C72852D4F49C864ACB7ADAFE2478E824AFE51C8919D06168414C265F298A8094B1AD813A9B8614ACABAC321F24CE61C5A5346EB519520D38ECC43E89B5000236DF0597243E4D2493FD626730E2BA17AC4D8824D09D1A4A8F57B8227778E2DE",
+         "7356CD7720D5B66B6D0697EB3177D9F8D73A4A5C5E968896EB6A6896843027066C23B601D3DDFB391E90D5C8ECCDEF4AE2A264BCE9E612BA15E2BC9D654AF1481B2E75DBABE615974F1070BBA84D56853265A34330B4766F8E75EDD1F4A1650476C10802F22B64BD3919D246BA20A17558BC51C199EFDEC67E80A227251808D8CE5BAD"),
+        (1025,
+         "D00278AE47EB27B34FAECF67B4FE263F82D5412916C1FFD97C8CB7FB814B8444F4C4A22B4B399155358A994E52BF255DE60035742EC71BD08AC275A1B51CC6BFE332B0EF84B409108CDA080E6269ED4B3E2C3F7D722AA4CDC98D16DEB554E5627BE8F955C98E1D5F9565A9194CAD0C4285F93700062D9595ADB992AE68FF12800AB67A",
+         "357DC55DE0C7E382C900FD6E320ACC04146BE01DB6A8CE7210B7189BD664EA69362396B77FDC0D2634A552970843722066C3C15902AE5097E00FF53F1E116F1CD5352720113A837AB2452CAFBDE4D54085D9CF5D21CA613071551B25D52E69D6C81123872B6F19CD3BC1333EDF0C52B94DE23BA772CF82636CFF4542540A7738D5B930",
+         "EFFAA245F065FBF82AC186839A249707C3BDDF6D3FDDA22D1B95A3C970379BCB5D31013A167509E9066273AB6E2123BC835B408B067D88F96ADDB550D96B6852DAD38E320B9D940F86DB74D398C770F462118B35D2724EFA13DA97194491D96DD37C3C09CBEF665953F2EE85EC83D88B88D11547A6F911C8217CCA46DEFA2751E7F3AD"),
+        (2049,
+         "5F4D72F40D7A5F82B15CA2B2E44B1DE3C2EF86C426C95C1AF0B687952256303096DE31D71D74103403822A2E0BC1EB193E7AECC9643A76B7BBC0C9F9C52E8783AAE98764CA468962B5C2EC92F0C74EB5448D519713E09413719431C802F948DD5D90425A4ECDADECE9EB178D80F26EFCCAE630734DFF63340285ADEC2AED3B51073AD3",
+         "9F29700902F7C86E514DDC4DF1E3049F258B2472B6DD5267F61BF13983B78DD5F9A88ABFEFDFA1E00B418971F2B39C64CA621E8EB37FCEAC57FD0C8FC8E117D43B81447BE22D5D8186F8F5919BA6BCC6846BD7D50726C06D245672C2AD4F61702C646499EE1173DAA061FFE15BF45A631E2946D616A4C345822F1151284712F76B2B0E",
+         "2EA477C5515CC3DD606512EE72BB3E0E758CFAE7232826F35FB98CA1BCBDF27316D8E9E79081A80B046B60F6A263616F33CA464BD78D79FA18200D06C7FC9BFFD808CC4755277A7D5E09DA0F29ED150F6537EA9BED946227FF184CC66A72A5F8C1E4BD8B04E81CF40FE6DC4427AD5678311A61F4FFC39D195589BDBC670F63AE70F4B6"),
+        (3073,
+         "7124B49501012F81CC7F11CA069EC9226CECB8A2C850CFE644E327D22D3E1CD39A27AE3B79D68D89DA9BF25BC27139AE65A324918A5F9B7828181E52CF373C84F35B639B7FCCBB985B6F2FA56AEA0C18F531203497B8BBD3A07CEB5926F1CAB74D14BD66486D9A91EBA99059A98BD1CD25876B2AF5A76C3E9EED554ED72EA952B603BF",
+         "68DEDE9BEF00BA89E43F31A6825F4CF433389FEDAE75C04EE9F0CF16A427C95A96D6DA3FE985054D3478865BE9A092250839A697BBDA74E279E8A9E69F0025E4CFDDD6CFB434B1CD9543AAF97C635D1B451A4386041E4BB100F5E45407CBBC24FA53EA2DE3536CCB329E4EB9466EC37093A42CF62B82903C696A93A50B702C80F3C3C5",
+         "72613C9EC9FF7E40F8F5C173784C532AD852E827DBA2BF85B2AB4B76F7079081576288E552647A9D86481C2CAE75C2DD4E7C5195FB9ADA1EF50E9C5098C249D743929191441301C69E1F48505A4305EC1778450EE48B8E69DC23A25960FE33070EA549119599760A8A2D28AECA06B8C5E9BA58BC19E11FE57B6EE98AA44B2A8E6B14A5"),
+        (8193,
+         "BAB6C09CB8CE8CF459261398D2E7AEF35700BF488116CEB94A36D0F5F1B7BC3BB2282AA69BE089359EA1154B9A9286C4A56AF4DE975A9AA4A5C497654914D279BEA60BB6D2CF7225A2FA0FF5EF56BBE4B149F3ED15860F78B4E2AD04E158E375C1E0C0B551CD7DFC82F1B155C11B6B3ED51EC9EDB30D133653BB5709D1DBD55F4E1FF6",
+         "954A2A75420C8D6547E3BA5B98D963E6FA6491ADDC8C023189CC519821B4A1F5F03228648FD983AEF045C2FA8290934B0866B615F585149587DDA2299039965328835A2B18F1D63B7E300FC76FF260B571839FE44876A4EAE66CBAC8C67694411ED7E09DF51068A22C6E67D6D3DD2CCA8FF12E3275384006C80F4DB68023F24EEBBA57",
+         "AF1E0346E389B17C23200270A64AA4E1EAD98C61695D917DE7D5B00491C9B0F12F20A01D6D622EDF3DE026A4DB4E4526225DEBB93C1237934D71C7340BB5916158CBDAFE9AC3225476B6AB57A12357DB3ABBAD7A26C6E66290E44034FB08A20A8D0EC264F309994D2810C49CFBA6989D7ABB095897459F5425ADB48ABA07C5FB3C83C0"),
+        (31744,
+         "62B6960E1A44BCC1EB1A611A8D6235B6B4B78F32E7ABC4FB4C6CDCCE94895C47860CC51F2B0C28A7B77304BD55FE73AF663C02D3F52EA053BA43431CA5BAB7BFEA2F5E9D7121770D88F70AE9649EA713087D1914F7F312147E247F87EB2D4FFEF0AC978BF7B6579D57D533355AA20B8B77B13FD09748728A5CC327A8EC470F4013226F",
+         "EFA53B389AB67C593DBA624D898D0F7353AB99E4AC9D42302EE64CBF9939A4193A7258DB2D9CD32A7A3ECFCE46144114B15C2FCB68A618A976BD74515D47BE08B628BE420B5E830FADE7C080E351A076FBC38641AD80C736C8A18FE3C66CE12F95C61C2462A9770D60D0F77115BBCD3782B593016A4E728D4C06CEE4505CB0C08A42EC",
+         "39772AEF80E0EBE60596361E45B061E8F417429D529171B6764468C22928E28E9759ADEB797A3FBF771B1BCEA30150A020E317982BF0D6E7D14DD9F064BC11025C25F31E81BD78A921DB0174F03DD481D30E93FD8E90F8B2FEE209F849F2D2A52F31719A490FB0BA7AEA1E09814EE912EBA111A9FDE9D5C274185F7BAE8BA85D300A2B"),
+        (102400,
+         "BC3E3D41A1146B069ABFFAD3C0D44860CF664390AFCE4D9661F7902E7943E085E01C59DAB908C04C3342B816941A26D69C2605EBEE5EC5291CC55E15B76146E6745F0601156C3596CB75065A9C57F35585A52E1AC70F69131C23D611CE11EE4AB1EC2C009012D236648E77BE9295DD0426F29B764D65DE58EB7D01DD42248204F45F8E",
+         "1C35D1A5811083FD7119F5D5D1BA027B4D01C0C6C49FB6FF2CF75393EA5DB4A7F9DBDD3E1D81DCBCA3BA241BB18760F207710B751846FAAEB9DFF8262710999A59B2AA1ACA298A032D94EACFADF1AA192418EB54808DB23B56E34213266AA08499A16B354F018FC4967D05F8B9D2AD87A7278337BE9693FC638A3BFDBE314574EE6FC4",
+         "4652CFF7A3F385A6103B5C260FC1593E13C778DBE608EFB092FE7EE69DF6E9C6D83A3E041BC3A48DF2879F4A0A3ED40E7C961C73EFF740F3117A0504C2DFF4786D44FB17F1549EB0BA585E40EC29BF7732F0B7E286FF8ACDDC4CB1E23B87FF5D824A986458DCC6A04AC83969B80637562953DF51ED1A7E90A7926924D2763778BE8560"),
+    ];
+
+    fn input(buf: &mut [u8]) -> &[u8] {
+        for (i, x) in buf.iter_mut().enumerate() {
+            *x = (i % 251) as u8;
+        }
+        buf
+    }
+
+    #[test]
+    fn test_vectors() {
+        let mut buf = [0; 102400];
+        for &(len, h, k, d) in VECTORS {
+            let input = input(&mut buf[..len]);
+            for (mut state, expected) in [(Blake3::new(), h),
+                                          (Blake3::new_keyed(KEY), k),
+                                          (Blake3::new_derive_key(CONTEXT), d)] {
+                let expected = HEXUPPER.decode(expected.as_bytes()).unwrap();
+                state.update(input);
+                assert_eq!(&state.clone().finalize(), &expected[..32]);
+
+                let mut out = [0; 131];
+                state.finalize_xof().fill(&mut out);
+                assert_eq!(&out[..], &expected[..]);
+            }
+
+            assert_eq!(&hash(input), &HEXUPPER.decode(h.as_bytes()).unwrap()[..32]);
+            assert_eq!(&keyed_hash(KEY, input), &HEXUPPER.decode(k.as_bytes()).unwrap()[..32]);
+            assert_eq!(&derive_key(CONTEXT, input)[..],
+                       &HEXUPPER.decode(d.as_bytes()).unwrap()[..32]);
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let mut buf = [0; 5000];
+        let data = input(&mut buf);
+        for &len in &[63, 64, 65, 1023, 1024, 1025, 3000, 5000] {
+            let mut ctx = Blake3::new();
+            ctx.update(&data[..1]);
+            ctx.update(&data[1..len / 3]);
+            ctx.update(&data[len / 3..len]);
+            assert_eq!(ctx.finalize(), hash(&data[..len]));
+        }
+    }
+
+    #[test]
+    fn test_xof_pieces() {
+        let mut full = [0; 300];
+        Blake3::new().finalize_xof().fill(&mut full);
+
+        let mut xof = Blake3::new().finalize_xof();
+        let mut pieces = [0; 300];
+        xof.fill(&mut pieces[..10]);
+        xof.fill(&mut pieces[10..64]);
+        xof.fill(&mut pieces[64..200]);
+        assert_eq!(xof.position(), 200);
+        xof.fill(&mut pieces[200..]);
+        assert_eq!(&full[..], &pieces[..]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_on_drop() {
+        use core::mem::MaybeUninit;
+        use core::ptr;
+        use zeroize::Zeroizing;
+
+        let mut state = MaybeUninit::new(Blake3::new_keyed(KEY));
+        unsafe {
+            state.assume_init_mut().update(&[0xAA; 3000]);
+            ptr::drop_in_place(state.as_mut_ptr());
+            let state = &*state.as_ptr();
+            assert_eq!(state.key, [0; 8]);
+            assert!(state.cv_stack.iter().all(|cv| *cv == [0; 8]));
+            assert_eq!(state.chunk_state.cv, [0; 8]);
+            assert_eq!(state.chunk_state.block, [0; 16]);
+        }
+
+        let mut result = MaybeUninit::new(Zeroizing::new(keyed_hash(KEY, b"data")));
+        unsafe {
+            ptr::drop_in_place(result.as_mut_ptr());
+            let result = &*result.as_ptr();
+            assert_eq!(result.h, [0; super::OUT_LEN]);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! The original BLAKE-512 and BLAKE-384 hash functions.
+//!
+//! These are the 16-round BLAKE-512 and BLAKE-384 from the final round of
+//! the SHA-3 competition, as used by some older protocols. New designs
+//! should use BLAKE2b instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake512::{Blake512, blake512};
+//!
+//! // Using the convenience function.
+//! let hash = blake512(64, b"The quick brown fox jumps over the lazy dog");
+//!
+//! // Using the state context.
+//! let mut context = Blake512::new(64);
+//! context.update(b"The quick brown fox jumps over the lazy dog");
+//! let hash = context.finalize();
+//!
+//! // BLAKE-384.
+//! let hash = blake512(48, b"The quick brown fox jumps over the lazy dog");
+//! ```
+
+use blake2b::Blake2b;
+
+blake_impl!(Blake512, Blake512Result, blake512, Blake2b, u64, u64x4, u128,
+            64, 48, 16, 32, 25, 16, 11, [
+    0x6A09E667F3BCC908, 0xBB67AE8584CAA73B,
+    0x3C6EF372FE94F82B, 0xA54FF53A5F1D36F1,
+    0x510E527FADE682D1, 0x9B05688C2B3E6C1F,
+    0x1F83D9ABFB41BD6B, 0x5BE0CD19137E2179,
+], [
+    0xCBBB9D5DC1059ED8, 0x629A292A367CD507,
+    0x9159015A3070DD17, 0x152FECD8F70E5939,
+    0x67332667FFC00B31, 0x8EB44A8768581511,
+    0xDB0C2E0D64F98FA7, 0x47B5481DBEFA4FA4,
+], [
+    0x243F6A8885A308D3, 0x13198A2E03707344,
+    0xA4093822299F31D0, 0x082EFA98EC4E6C89,
+    0x452821E638D01377, 0xBE5466CF34E90C6C,
+    0xC0AC29B7C97C50DD, 0x3F84D5B5B5470917,
+    0x9216D5D98979FB1B, 0xD1310BA698DFB5AC,
+    0x2FFD72DBD01ADFB7, 0xB8E1AFED6A267E96,
+    0xBA7C9045F12C7F99, 0x24A19947B3916CF7,
+    0x0801F2E2858EFC16, 0x636920D871574E69,
+]);
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use super::{Blake512, blake512};
+
+    #[test]
+    fn test_official() {
+        assert_eq!(&blake512(64, &[0]), &HEXUPPER.decode(
+            b"97961587F6D970FABA6D2478045DE6D1FABD09B61AE50932054D52BC29D31BE4FF9102B9F69E2BBDB83BE13D4B9C06091E5FA0B48BD081B634058BE0EC49BEB3")
+            .unwrap()[..]);
+        assert_eq!(&blake512(64, &[0; 144]), &HEXUPPER.decode(
+            b"313717D608E9CF758DCB1EB0F0C3CF9FC150B2D500FB33F51C52AFC99D358A2F1374B8A38BBA7974E7F6EF79CAB16F22CE1E649D6E01AD9589C213045D545DDE")
+            .unwrap()[..]);
+        assert_eq!(&blake512(48, &[0]), &HEXUPPER.decode(
+            b"10281F67E135E90AE8E882251A355510A719367AD70227B137343E1BC122015C29391E8545B5272D13A7C2879DA3D807")
+            .unwrap()[..]);
+        assert_eq!(&blake512(48, &[0; 144]), &HEXUPPER.decode(
+            b"0B9845DD429566CDAB772BA195D271EFFE2D0211F16991D766BA749447C5CDE569780B2DAA66C4B224A2EC2E5D09174C")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(&Blake512::default().finalize(), &HEXUPPER.decode(
+            b"A8CFBBD73726062DF0C6864DDA65DEFE58EF0CC52A5625090FA17601E1EECD1B628E94F396AE402A00ACC9EAB77B4D4C2E852AAAA25A636D80AF3FC7913EF5B8")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_split() {
+        let data = selftest_seq(512);
+
+        for &len in &[111, 112, 127, 128, 129, 239, 240, 512] {
+            let mut ctx = Blake512::new(64);
+            ctx.update(&data[..3]);
+            ctx.update(&data[3..len / 2]);
+            ctx.update(&data[len / 2..len]);
+            assert_eq!(&ctx.finalize(), &blake512(64, &data[..len]));
+        }
+    }
+}
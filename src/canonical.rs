@@ -0,0 +1,530 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Hashing of `serde` values with a canonical encoding.
+//!
+//! The `Serializer` in this module feeds a hashing context directly with
+//! an encoding of any value implementing `Serialize`, without allocating
+//! memory. Each value is encoded as a one-byte tag for its kind, followed
+//! by its contents:
+//!
+//! | Kind | Tag | Contents |
+//! |------|-----|----------|
+//! | `bool` | `0x01` | one byte, `0x00` or `0x01` |
+//! | `i8`, `i16`, `i32`, `i64`, `i128` | `0x02` to `0x06` | little-endian |
+//! | `u8`, `u16`, `u32`, `u64`, `u128` | `0x07` to `0x0B` | little-endian |
+//! | `f32`, `f64` | `0x0C`, `0x0D` | bits, little-endian |
+//! | `char` | `0x0E` | code point, little-endian `u32` |
+//! | string | `0x0F` | length, UTF-8 bytes |
+//! | bytes | `0x10` | length, bytes |
+//! | `None` | `0x11` | nothing |
+//! | `Some` | `0x12` | value |
+//! | unit | `0x13` | nothing |
+//! | unit struct | `0x14` | nothing |
+//! | unit variant | `0x15` | variant index |
+//! | newtype struct | `0x16` | value |
+//! | newtype variant | `0x17` | variant index, value |
+//! | sequence | `0x18` | list of values |
+//! | tuple | `0x19` | list of values |
+//! | tuple struct | `0x1A` | list of values |
+//! | tuple variant | `0x1B` | variant index, list of values |
+//! | map | `0x1C` | list of key and value pairs |
+//! | struct | `0x1D` | list of field name and value pairs |
+//! | struct variant | `0x1E` | variant index, list of field name and value pairs |
+//!
+//! Lengths are little-endian `u64`, and variant indexes are little-endian
+//! `u32`. Field names are encoded as their length followed by their UTF-8
+//! bytes, without a tag. In a list, each item is preceded by a `0x01` byte,
+//! and the list ends with a `0x00` byte, so the length of a sequence or a
+//! map doesn't have to be known in advance.
+//!
+//! Since the encoding of every value can be decoded without knowing where
+//! it ends, distinct values (as seen by `serde`) always have distinct
+//! encodings. The names of types and variants are not part of the
+//! encoding, so renaming them doesn't change the hashes, but the names of
+//! struct fields are. Maps are encoded in the order they are iterated, so
+//! to have the same hash for the same contents, use a map with a defined
+//! order like `BTreeMap`.
+//!
+//! This encoding will not change in a compatible version of this crate.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2b::Blake2b;
+//! use blake2_rfc::canonical;
+//!
+//! let mut context = Blake2b::new(64);
+//! canonical::update(&mut context, &("key", [1u32, 2, 3], Some(true))).unwrap();
+//! let hash = context.finalize();
+//! ```
+
+use core::fmt::{self, Display, Write};
+
+use serde::ser::{self, Serialize};
+
+use blake2b::Blake2b;
+use blake2bp::Blake2bp;
+use blake2s::Blake2s;
+use blake2sp::Blake2sp;
+
+/// A hashing context which can be fed by the `Serializer`.
+pub trait Update {
+    /// Hashes a piece of data.
+    fn update(&mut self, data: &[u8]);
+}
+
+macro_rules! update_impl {
+    ($($state:ident),*) => {
+        $(
+            impl Update for $state {
+                #[inline]
+                fn update(&mut self, data: &[u8]) { $state::update(self, data) }
+            }
+        )*
+    }
+}
+
+update_impl!(Blake2b, Blake2s, Blake2bp, Blake2sp);
+
+/// Hashes the canonical encoding of a value.
+pub fn update<S, T>(state: &mut S, value: &T) -> Result<(), Error>
+    where S: Update + ?Sized, T: Serialize + ?Sized
+{
+    value.serialize(&mut Serializer::new(state))
+}
+
+/// Error returned by a `Serialize` implementation.
+#[derive(Clone, Debug)]
+pub struct Error {
+    #[cfg(feature = "std")]
+    msg: ::std::string::String,
+}
+
+impl Display for Error {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("serialization error")
+    }
+}
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    #[cfg(feature = "std")]
+    fn custom<T: Display>(msg: T) -> Self {
+        Error { msg: ::std::string::ToString::to_string(&msg) }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn custom<T: Display>(_msg: T) -> Self {
+        Error {}
+    }
+}
+
+const ITEM: u8 = 0x01;
+const END: u8 = 0x00;
+
+/// Serializer which hashes the canonical encoding of the values.
+#[derive(Debug)]
+pub struct Serializer<'a, S: ?Sized + 'a> {
+    state: &'a mut S,
+}
+
+impl<'a, S: Update + ?Sized> Serializer<'a, S> {
+    /// Creates a serializer which feeds a hashing context.
+    pub fn new(state: &'a mut S) -> Self {
+        Serializer { state }
+    }
+
+    #[inline]
+    fn tag(&mut self, tag: u8) {
+        self.state.update(&[tag]);
+    }
+
+    #[inline]
+    fn len(&mut self, len: usize) {
+        self.state.update(&(len as u64).to_le_bytes());
+    }
+
+    #[inline]
+    fn variant(&mut self, tag: u8, index: u32) {
+        self.tag(tag);
+        self.state.update(&index.to_le_bytes());
+    }
+
+    #[inline]
+    fn field(&mut self, name: &str) {
+        self.len(name.len());
+        self.state.update(name.as_bytes());
+    }
+}
+
+/// Counts or hashes the output of `Display`.
+struct DisplayWriter<'a, S: ?Sized + 'a> {
+    state: Option<&'a mut S>,
+    len: usize,
+}
+
+impl<'a, S: Update + ?Sized> Write for DisplayWriter<'a, S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(ref mut state) = self.state {
+            state.update(s.as_bytes());
+        }
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+macro_rules! serialize_int {
+    ($($method:ident($ty:ty) = $tag:expr),*) => {
+        $(
+            #[inline]
+            fn $method(self, v: $ty) -> Result<(), Error> {
+                self.tag($tag);
+                self.state.update(&v.to_le_bytes());
+                Ok(())
+            }
+        )*
+    }
+}
+
+impl<'a, 'b, S: Update + ?Sized> ser::Serializer for &'b mut Serializer<'a, S> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.tag(0x01);
+        self.state.update(&[v as u8]);
+        Ok(())
+    }
+
+    serialize_int!(serialize_i8(i8) = 0x02, serialize_i16(i16) = 0x03,
+                   serialize_i32(i32) = 0x04, serialize_i64(i64) = 0x05,
+                   serialize_i128(i128) = 0x06,
+                   serialize_u8(u8) = 0x07, serialize_u16(u16) = 0x08,
+                   serialize_u32(u32) = 0x09, serialize_u64(u64) = 0x0A,
+                   serialize_u128(u128) = 0x0B);
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.tag(0x0C);
+        self.state.update(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.tag(0x0D);
+        self.state.update(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.tag(0x0E);
+        self.state.update(&(v as u32).to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.tag(0x0F);
+        self.field(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.tag(0x10);
+        self.len(v.len());
+        self.state.update(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.tag(0x11);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        self.tag(0x12);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.tag(0x13);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.tag(0x14);
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32,
+                              _variant: &'static str) -> Result<(), Error> {
+        self.variant(0x15, variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T)
+                                   -> Result<(), Error>
+        where T: Serialize + ?Sized
+    {
+        self.tag(0x16);
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, variant_index: u32,
+                                    _variant: &'static str, value: &T)
+                                    -> Result<(), Error>
+        where T: Serialize + ?Sized
+    {
+        self.variant(0x17, variant_index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Error> {
+        self.tag(0x18);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        self.tag(0x19);
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize)
+                              -> Result<Self, Error> {
+        self.tag(0x1A);
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32,
+                               _variant: &'static str, _len: usize)
+                               -> Result<Self, Error> {
+        self.variant(0x1B, variant_index);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, Error> {
+        self.tag(0x1C);
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize)
+                        -> Result<Self, Error> {
+        self.tag(0x1D);
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32,
+                                _variant: &'static str, _len: usize)
+                                -> Result<Self, Error> {
+        self.variant(0x1E, variant_index);
+        Ok(self)
+    }
+
+    /// Encoded as a string. The value is formatted twice, first to find
+    /// the length and then to hash it, so no memory has to be allocated.
+    fn collect_str<T: Display + ?Sized>(self, value: &T) -> Result<(), Error> {
+        let mut counter = DisplayWriter::<S> { state: None, len: 0 };
+        write!(counter, "{}", value).map_err(|_| <Error as ser::Error>::custom("fmt error"))?;
+        let len = counter.len;
+
+        self.tag(0x0F);
+        self.len(len);
+        let mut writer = DisplayWriter { state: Some(&mut *self.state), len: 0 };
+        write!(writer, "{}", value).map_err(|_| <Error as ser::Error>::custom("fmt error"))?;
+        if writer.len != len {
+            return Err(<Error as ser::Error>::custom("inconsistent Display output"));
+        }
+        Ok(())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! serialize_list {
+    ($($trait_:ident::$method:ident),*) => {
+        $(
+            impl<'a, 'b, S: Update + ?Sized> ser::$trait_ for &'b mut Serializer<'a, S> {
+                type Ok = ();
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T)
+                                                  -> Result<(), Error> {
+                    self.tag(ITEM);
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    self.tag(END);
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+serialize_list!(SerializeSeq::serialize_element,
+                SerializeTuple::serialize_element,
+                SerializeTupleStruct::serialize_field,
+                SerializeTupleVariant::serialize_field);
+
+impl<'a, 'b, S: Update + ?Sized> ser::SerializeMap for &'b mut Serializer<'a, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.tag(ITEM);
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.tag(END);
+        Ok(())
+    }
+}
+
+macro_rules! serialize_fields {
+    ($($trait_:ident),*) => {
+        $(
+            impl<'a, 'b, S: Update + ?Sized> ser::$trait_ for &'b mut Serializer<'a, S> {
+                type Ok = ();
+                type Error = Error;
+
+                fn serialize_field<T>(&mut self, key: &'static str, value: &T)
+                                      -> Result<(), Error>
+                    where T: Serialize + ?Sized
+                {
+                    self.tag(ITEM);
+                    self.field(key);
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    self.tag(END);
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+serialize_fields!(SerializeStruct, SerializeStructVariant);
+
+#[cfg(test)]
+mod tests {
+    use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+    use blake2b::{Blake2b, blake2b};
+    use super::{Update, update};
+
+    struct Recorder {
+        buf: [u8; 256],
+        len: usize,
+    }
+
+    impl Update for Recorder {
+        fn update(&mut self, data: &[u8]) {
+            self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+        }
+    }
+
+    fn encode<T: Serialize + ?Sized>(value: &T, f: &mut dyn FnMut(&[u8])) {
+        let mut recorder = Recorder { buf: [0; 256], len: 0 };
+        update(&mut recorder, value).unwrap();
+        f(&recorder.buf[..recorder.len]);
+    }
+
+    struct Point {
+        x: i16,
+        label: Option<&'static str>,
+    }
+
+    impl Serialize for Point {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Point", 2)?;
+            s.serialize_field("x", &self.x)?;
+            s.serialize_field("label", &self.label)?;
+            s.end()
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        encode(&(true, 'a', [1u8, 2]), &mut |bytes| assert_eq!(bytes, [
+            0x19,
+            0x01, 0x01, 0x01,
+            0x01, 0x0E, 0x61, 0x00, 0x00, 0x00,
+            0x01, 0x19, 0x01, 0x07, 0x01, 0x01, 0x07, 0x02, 0x00,
+            0x00,
+        ].as_ref()));
+
+        let point = Point { x: -2, label: Some("ab") };
+        encode(&point, &mut |bytes| assert_eq!(bytes, [
+            0x1D,
+            0x01, 0x01, 0, 0, 0, 0, 0, 0, 0, b'x', 0x03, 0xFE, 0xFF,
+            0x01, 0x05, 0, 0, 0, 0, 0, 0, 0, b'l', b'a', b'b', b'e', b'l',
+            0x12, 0x0F, 0x02, 0, 0, 0, 0, 0, 0, 0, b'a', b'b',
+            0x00,
+        ].as_ref()));
+    }
+
+    #[test]
+    fn test_collect_str() {
+        let mut a = Blake2b::new(64);
+        update(&mut a, &format_args!("{}-{}", 12, "ab")).unwrap();
+        let mut b = Blake2b::new(64);
+        update(&mut b, "12-ab").unwrap();
+        assert_eq!(a.finalize(), b.finalize());
+    }
+
+    #[test]
+    fn test_distinct() {
+        fn hash<T: Serialize + ?Sized>(value: &T) -> [u8; 64] {
+            let mut hash = [0; 64];
+            encode(value, &mut |bytes| {
+                hash.copy_from_slice(blake2b(64, &[], bytes).as_bytes());
+            });
+            hash
+        }
+
+        assert_ne!(hash(&("ab", "c")), hash(&("a", "bc")));
+        assert_ne!(hash(&[&[1u8][..], &[]]), hash(&[&[][..], &[1u8]]));
+        assert_ne!(hash(&Some(())), hash(&()));
+        assert_ne!(hash(&1u32), hash(&1u64));
+        assert_ne!(hash(&(1u8, 2u8)), hash(&[1u8, 2][..]));
+    }
+
+    #[test]
+    fn test_hash() {
+        let point = Point { x: 7, label: None };
+        let mut context = Blake2b::new(64);
+        update(&mut context, &point).unwrap();
+        encode(&point, &mut |bytes| {
+            assert_eq!(context.clone().finalize(), blake2b(64, &[], bytes));
+        });
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! C API compatible with the `blake2.h` header of libb2.
+//!
+//! With the `capi` cargo feature, this crate exports the functions of
+//! libb2 for BLAKE2b and BLAKE2s, and the one-shot functions for BLAKE2bp
+//! and BLAKE2sp, with the same names, arguments and return values (`0` on
+//! success, `-1` on error).
+//!
+//! The `capi` feature doesn't change the crate type, so `cargo build`
+//! still builds only a Rust library. To build a C library, use for
+//! instance `cargo rustc --release --features capi --crate-type cdylib`
+//! (or `staticlib`), together with the `include/blake2.h` header. That
+//! header is maintained by hand, not generated from this module, so it
+//! must be updated together with it; the `capi` tests check that they
+//! match.
+//!
+//! The parameter blocks have the same layout as in libb2. The states have
+//! the same size and alignment as in libb2, but their contents are opaque.
+//! As in the reference implementation, `blake2b_final` and `blake2s_final`
+//! fail if `outlen` is smaller than the output length given at
+//! initialization, and write only that many bytes; calling them twice on
+//! the same state also fails.
+
+#![allow(non_camel_case_types)]
+
+use core::ffi::{c_int, c_void};
+use core::mem;
+use core::ptr;
+use core::slice;
+
+use blake2b::Blake2b;
+use blake2bp::Blake2bp;
+use blake2s::Blake2s;
+use blake2sp::Blake2sp;
+
+/// BLAKE2s parameter block (`blake2s_param`).
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug)]
+pub struct blake2s_param {
+    /// Digest length.
+    pub digest_length: u8,
+    /// Key length.
+    pub key_length: u8,
+    /// Fanout.
+    pub fanout: u8,
+    /// Maximal depth.
+    pub depth: u8,
+    /// Leaf maximal byte length.
+    pub leaf_length: u32,
+    /// Node offset.
+    pub node_offset: [u8; 6],
+    /// Node depth.
+    pub node_depth: u8,
+    /// Inner hash byte length.
+    pub inner_length: u8,
+    /// Salt.
+    pub salt: [u8; 8],
+    /// Personalization.
+    pub personal: [u8; 8],
+}
+
+/// BLAKE2b parameter block (`blake2b_param`).
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug)]
+pub struct blake2b_param {
+    /// Digest length.
+    pub digest_length: u8,
+    /// Key length.
+    pub key_length: u8,
+    /// Fanout.
+    pub fanout: u8,
+    /// Maximal depth.
+    pub depth: u8,
+    /// Leaf maximal byte length.
+    pub leaf_length: u32,
+    /// Node offset.
+    pub node_offset: u64,
+    /// Node depth.
+    pub node_depth: u8,
+    /// Inner hash byte length.
+    pub inner_length: u8,
+    /// Reserved, must be zero.
+    pub reserved: [u8; 14],
+    /// Salt.
+    pub salt: [u8; 16],
+    /// Personalization.
+    pub personal: [u8; 16],
+}
+
+/// Opaque BLAKE2s state (`blake2s_state`).
+#[repr(C, align(64))]
+pub struct blake2s_state {
+    opaque: [u8; 192],
+}
+
+/// Opaque BLAKE2b state (`blake2b_state`).
+#[repr(C, align(64))]
+pub struct blake2b_state {
+    opaque: [u8; 384],
+}
+
+/// Returns the input as a slice, or `None` for a null pointer with a
+/// nonzero length.
+unsafe fn input<'a>(data: *const c_void, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data as *const u8, len))
+    }
+}
+
+macro_rules! capi_impl {
+    ($state:ident, $param:ident, $ctx:ident, $bytes:expr,
+     $init:ident, $init_key:ident, $init_param:ident, $update:ident,
+     $final:ident, $func:ident, $pfunc:ident, $pctx:ident) => {
+        // The hashing context is stored within the C state, with `None`
+        // after finalization.
+        const _: () = assert!(mem::size_of::<Option<$ctx>>() <= mem::size_of::<$state>() &&
+                              mem::align_of::<Option<$ctx>>() <= mem::align_of::<$state>());
+        const _: () = assert!(mem::size_of::<$param>() == $bytes);
+
+        unsafe fn store(state: *mut $state, ctx: $ctx) -> c_int {
+            ptr::write(state as *mut Option<$ctx>, Some(ctx));
+            0
+        }
+
+        #[doc = concat!("Initializes a state (`", stringify!($init), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $init(state: *mut $state, outlen: usize) -> c_int {
+            $init_key(state, outlen, ptr::null(), 0)
+        }
+
+        #[doc = concat!("Initializes a state with a key (`", stringify!($init_key), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $init_key(state: *mut $state, outlen: usize,
+                                           key: *const c_void, keylen: usize)
+                                           -> c_int {
+            let key = match input(key, keylen) {
+                Some(key) if !state.is_null() && keylen <= $bytes
+                    && (1..=$bytes).contains(&outlen) => key,
+                _ => return -1,
+            };
+            store(state, $ctx::with_key(outlen, key))
+        }
+
+        #[doc = concat!("Initializes a state with a parameter block (`",
+                        stringify!($init_param), "`).")]
+        ///
+        /// As in libb2, the key block is not hashed even if the key
+        /// length is not zero.
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $init_param(state: *mut $state,
+                                             param: *const $param) -> c_int {
+            if state.is_null() || param.is_null() {
+                return -1;
+            }
+            let p = ptr::read(param as *const [u8; $bytes]);
+            if p[0] == 0 || p[0] as usize > $bytes || p[1] as usize > $bytes {
+                return -1;
+            }
+
+            let mut words = [0; 8];
+            for (word, bytes) in words.iter_mut().zip(p.chunks($bytes / 8)) {
+                let mut buf = [0; $bytes / 8];
+                buf.copy_from_slice(bytes);
+                *word = FromLe::from_le_bytes(buf);
+            }
+            store(state, $ctx::with_parameter_block(&words))
+        }
+
+        #[doc = concat!("Hashes more data (`", stringify!($update), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $update(state: *mut $state, data: *const u8,
+                                         inlen: usize) -> c_int {
+            match (state.is_null(), input(data as *const c_void, inlen)) {
+                (false, Some(data)) => match *(state as *mut Option<$ctx>) {
+                    Some(ref mut ctx) => { ctx.update(data); 0 }
+                    None => -1,
+                },
+                _ => -1,
+            }
+        }
+
+        #[doc = concat!("Finalizes the state and writes the hash (`", stringify!($final), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $final(state: *mut $state, out: *mut u8,
+                                        outlen: usize) -> c_int {
+            if state.is_null() || out.is_null() {
+                return -1;
+            }
+            let ctx = &mut *(state as *mut Option<$ctx>);
+            let result = match *ctx {
+                Some(ref ctx) => ctx.clone().finalize(),
+                None => return -1,
+            };
+            if outlen < result.len() {
+                return -1;
+            }
+            *ctx = None;
+            ptr::copy_nonoverlapping(result.as_bytes().as_ptr(), out,
+                                     result.len());
+            0
+        }
+
+        #[doc = concat!("Computes a hash in a single call (`", stringify!($func), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $func(out: *mut u8, data: *const c_void,
+                                       key: *const c_void, outlen: usize,
+                                       inlen: usize, keylen: usize) -> c_int {
+            let mut ctx = mem::MaybeUninit::<$state>::uninit();
+            if $init_key(ctx.as_mut_ptr(), outlen, key, keylen) != 0
+                || $update(ctx.as_mut_ptr(), data as *const u8, inlen) != 0 {
+                return -1;
+            }
+            $final(ctx.as_mut_ptr(), out, outlen)
+        }
+
+        #[doc = concat!("Computes a parallel hash in a single call (`", stringify!($pfunc), "`).")]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid for the given lengths, as in libb2.
+        #[no_mangle]
+        pub unsafe extern "C" fn $pfunc(out: *mut u8, data: *const c_void,
+                                        key: *const c_void, outlen: usize,
+                                        inlen: usize, keylen: usize) -> c_int {
+            match (input(data, inlen), input(key, keylen)) {
+                (Some(data), Some(key)) if !out.is_null() && keylen <= $bytes
+                    && (1..=$bytes).contains(&outlen) => {
+                    let mut ctx = $pctx::with_key(outlen, key);
+                    ctx.update(data);
+                    let result = ctx.finalize();
+                    ptr::copy_nonoverlapping(result.as_bytes().as_ptr(), out,
+                                             outlen);
+                    0
+                }
+                _ => -1,
+            }
+        }
+    }
+}
+
+trait FromLe<T> {
+    fn from_le_bytes(bytes: T) -> Self;
+}
+
+impl FromLe<[u8; 4]> for u32 {
+    fn from_le_bytes(bytes: [u8; 4]) -> Self { u32::from_le_bytes(bytes) }
+}
+
+impl FromLe<[u8; 8]> for u64 {
+    fn from_le_bytes(bytes: [u8; 8]) -> Self { u64::from_le_bytes(bytes) }
+}
+
+mod b {
+    use super::*;
+    capi_impl!(blake2b_state, blake2b_param, Blake2b, 64,
+               blake2b_init, blake2b_init_key, blake2b_init_param,
+               blake2b_update, blake2b_final, blake2b, blake2bp, Blake2bp);
+}
+
+mod s {
+    use super::*;
+    capi_impl!(blake2s_state, blake2s_param, Blake2s, 32,
+               blake2s_init, blake2s_init_key, blake2s_init_param,
+               blake2s_update, blake2s_final, blake2s, blake2sp, Blake2sp);
+}
+
+pub use self::b::{blake2b_init, blake2b_init_key, blake2b_init_param};
+pub use self::b::{blake2b_update, blake2b_final, blake2b, blake2bp};
+pub use self::s::{blake2s_init, blake2s_init_key, blake2s_init_param};
+pub use self::s::{blake2s_update, blake2s_final, blake2s, blake2sp};
@@ -0,0 +1,317 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Deterministic random bit generator based on keyed BLAKE2b.
+//!
+//! `Drbg` follows the structure of HMAC_DRBG from NIST SP 800-90A, with
+//! keyed BLAKE2b (with a 64-byte key and output) instead of HMAC: its
+//! state is a key `K` and a value `V`, which are updated with
+//!
+//! ```text
+//! K = BLAKE2b(key = K, V || 0x00 || provided_data)
+//! V = BLAKE2b(key = K, V)
+//! ```
+//!
+//! as in `HMAC_DRBG_Update`, and, if `provided_data` is not empty, a
+//! second time with `0x01` instead of `0x00`. Instantiation starts with
+//! `K` all zeros and `V` all ones, and updates the state with the entropy
+//! input, the nonce and the personalization string; reseeding updates the
+//! state with the entropy input and the additional input.
+//!
+//! Each request generates the output with BLAKE2Xb (BLAKE2b for requests
+//! up to 64 bytes) keyed with `K`, with `V` as the input and
+//! `blake2-rfc drbg` as the personalization, and then updates the state
+//! with the additional input.
+//!
+//! With the `rand_core` cargo feature, `Drbg` implements `RngCore`,
+//! `CryptoRng` and `SeedableRng`.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::drbg::Drbg;
+//!
+//! let mut drbg = Drbg::new(b"entropy input of at least 32 bytes!",
+//!                          b"nonce", b"personalization");
+//! let mut key = [0; 32];
+//! drbg.generate(&mut key, b"").unwrap();
+//! ```
+
+use core::fmt;
+
+use blake2b::{Blake2b, Params};
+use kdf::blake2x;
+
+/// Length of the key and the value of the state.
+const STATE_LEN: usize = 64;
+
+/// Personalization of the output.
+const OUTPUT_PERSONAL: &[u8] = b"blake2-rfc drbg";
+
+/// Maximum length of a single request (`max_number_of_bits_per_request`).
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// Default maximum number of requests between reseeds (`reseed_interval`).
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// Error returned by `Drbg::generate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The reseed interval was reached; `reseed` must be called.
+    ReseedRequired,
+    /// The request is longer than `MAX_REQUEST_LEN`.
+    RequestTooLong,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::ReseedRequired => "DRBG reseed required",
+            Error::RequestTooLong => "DRBG request too long",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+/// Deterministic random bit generator.
+///
+/// With the `zeroize` cargo feature, the state is wiped when dropped.
+#[derive(Clone)]
+pub struct Drbg {
+    k: [u8; STATE_LEN],
+    v: [u8; STATE_LEN],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl Drbg {
+    /// Instantiates the generator.
+    ///
+    /// The entropy input must have at least 32 bytes of entropy for the
+    /// generator to have a 256-bit security strength.
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let mut drbg = Drbg {
+            k: [0; STATE_LEN],
+            v: [1; STATE_LEN],
+            reseed_counter: 1,
+            reseed_interval: RESEED_INTERVAL,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+        drbg
+    }
+
+    /// Sets the maximum number of requests between reseeds.
+    pub fn set_reseed_interval(&mut self, interval: u64) {
+        self.reseed_interval = interval;
+    }
+
+    /// Reseeds the generator with a new entropy input.
+    pub fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+        self.update(&[entropy, additional]);
+        self.reseed_counter = 1;
+    }
+
+    /// Fills `out` with pseudorandom bytes.
+    pub fn generate(&mut self, out: &mut [u8], additional: &[u8])
+                    -> Result<(), Error> {
+        if out.len() > MAX_REQUEST_LEN {
+            return Err(Error::RequestTooLong);
+        }
+        if self.reseed_counter > self.reseed_interval {
+            return Err(Error::ReseedRequired);
+        }
+
+        if !additional.is_empty() {
+            self.update(&[additional]);
+        }
+        if !out.is_empty() {
+            blake2x(out, &self.k, &[], OUTPUT_PERSONAL, &self.v);
+        }
+        self.update(&[additional]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// Reseeds the generator and then fills `out` with pseudorandom
+    /// bytes, for prediction resistance.
+    pub fn generate_with_reseed(&mut self, out: &mut [u8], entropy: &[u8],
+                                additional: &[u8]) -> Result<(), Error> {
+        self.reseed(entropy, additional);
+        self.generate(out, &[])
+    }
+
+    fn prf(&self, parts: &[&[u8]]) -> [u8; STATE_LEN] {
+        let mut state: Blake2b = Params::new(STATE_LEN).to_state(Some(&self.k));
+        for part in parts {
+            state.update(part);
+        }
+        let mut out = [0; STATE_LEN];
+        out.copy_from_slice(state.finalize().as_bytes());
+        out
+    }
+
+    fn update(&mut self, provided: &[&[u8]]) {
+        for &round in &[0x00, 0x01] {
+            let round = [round];
+            let mut parts = [&[][..]; 5];
+            parts[0] = &self.v;
+            parts[1] = &round;
+            parts[2..2 + provided.len()].copy_from_slice(provided);
+            self.k = self.prf(&parts);
+            self.v = self.prf(&[&self.v]);
+
+            if provided.iter().all(|data| data.is_empty()) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Drbg {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.k.zeroize();
+        self.v.zeroize();
+    }
+}
+
+impl fmt::Debug for Drbg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Drbg")
+            .field("reseed_counter", &self.reseed_counter)
+            .field("reseed_interval", &self.reseed_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "rand_core")]
+mod rng {
+    use rand_core::{CryptoRng, RngCore, SeedableRng};
+
+    use super::{Drbg, MAX_REQUEST_LEN};
+
+    impl RngCore for Drbg {
+        fn next_u32(&mut self) -> u32 {
+            let mut buf = [0; 4];
+            self.fill_bytes(&mut buf);
+            u32::from_le_bytes(buf)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut buf = [0; 8];
+            self.fill_bytes(&mut buf);
+            u64::from_le_bytes(buf)
+        }
+
+        /// Generates the bytes in requests of at most `MAX_REQUEST_LEN`
+        /// bytes.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the reseed interval is reached.
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for part in dest.chunks_mut(MAX_REQUEST_LEN) {
+                self.generate(part, &[]).expect("DRBG reseed required");
+            }
+        }
+    }
+
+    impl CryptoRng for Drbg {}
+
+    impl SeedableRng for Drbg {
+        type Seed = [u8; 32];
+
+        /// Instantiates the generator with the seed as the entropy input,
+        /// and an empty nonce and personalization string.
+        fn from_seed(seed: Self::Seed) -> Self {
+            Drbg::new(&seed, &[], &[])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use super::{Drbg, Error, MAX_REQUEST_LEN};
+
+    const ENTROPY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_generate() {
+        let mut drbg = Drbg::new(ENTROPY, b"nonce", b"personalization");
+        let mut out = [0; 32];
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"080580AEC5D1E70B3A89BF9C13A783C51D4B46CB0C518F7B3F448E5533FC53ED")
+            .unwrap()[..]);
+
+        let mut out = [0; 100];
+        drbg.generate(&mut out, b"additional").unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"7FD990857FA061BC243C49780F35315B5DD2FA2BA36DD2BA8EB31F1A75B0B52B31D42977259CCCAB80C3A3CBE4E21B4B6578B8B69DD1A507F926228902E232B54C7CB643F6A15E83C26B4716DEE0B6EA15BC141416FBFD3C626B0762947486218D9E40D7")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_reseed() {
+        let mut drbg = Drbg::new(ENTROPY, &[], &[]);
+        drbg.reseed(b"more entropy", b"additional");
+        let mut out = [0; 16];
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"9AA0FC31795B956DF597E7EB0B3ED6CA")
+            .unwrap()[..]);
+
+        let mut drbg = Drbg::new(ENTROPY, &[], &[]);
+        let mut out2 = [0; 16];
+        drbg.generate_with_reseed(&mut out2, b"more entropy", b"additional")
+            .unwrap();
+        assert_eq!(out, out2);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut drbg = Drbg::new(ENTROPY, &[], &[]);
+        drbg.set_reseed_interval(2);
+        let mut out = [0; 8];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(drbg.generate(&mut out, &[]), Err(Error::ReseedRequired));
+        drbg.reseed(ENTROPY, &[]);
+        drbg.generate(&mut out, &[]).unwrap();
+
+        let mut out = [0; MAX_REQUEST_LEN + 1];
+        assert_eq!(drbg.generate(&mut out, &[]), Err(Error::RequestTooLong));
+    }
+
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn test_rng() {
+        use rand_core::{RngCore, SeedableRng};
+
+        let mut seed = [0; 32];
+        seed.copy_from_slice(ENTROPY);
+        let mut rng = Drbg::from_seed(seed);
+        let mut drbg = Drbg::new(ENTROPY, &[], &[]);
+
+        let mut out = [0; 8];
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(rng.next_u64(), u64::from_le_bytes(out));
+
+        let mut a = [0; MAX_REQUEST_LEN + 10];
+        let mut b = [0; MAX_REQUEST_LEN + 10];
+        rng.fill_bytes(&mut a);
+        drbg.generate(&mut b[..MAX_REQUEST_LEN], &[]).unwrap();
+        drbg.generate(&mut b[MAX_REQUEST_LEN..], &[]).unwrap();
+        assert_eq!(&a[..], &b[..]);
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Text encodings of hash results.
+//!
+//! The hash results format as lowercase hexadecimal with `Display` and
+//! `LowerHex`, and as uppercase hexadecimal with `UpperHex`; `FromStr`
+//! parses hexadecimal in either case. The `base64` and `base32` methods
+//! of the hash results return a value which formats as padded base64 or
+//! base32 (with the standard alphabets from RFC 4648), and
+//! `from_base64` and `from_base32` parse them back.
+//!
+//! Parsing is strict: padding is required, and the unused bits of the
+//! last character must be zero, so each hash result has a single
+//! encoding. The length of the decoded result must be a valid hash
+//! length; it is not checked against any particular output length.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2b::{Blake2bResult, blake2b};
+//!
+//! let hash = blake2b(32, &[], b"abc");
+//! let hex = hash.to_string();
+//! assert_eq!(hex.parse::<Blake2bResult>().unwrap(), hash);
+//!
+//! let base64 = hash.base64().to_string();
+//! assert_eq!(Blake2bResult::from_base64(&base64).unwrap(), hash);
+//! ```
+
+use core::fmt;
+
+/// Error returned when parsing or converting a hash result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The length is not a valid hash length, or not a valid length
+    /// for the encoding.
+    InvalidLength,
+    /// The string contains a character outside of the encoding, or
+    /// non-canonical padding bits.
+    InvalidCharacter,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::InvalidLength => "invalid hash length",
+            Error::InvalidCharacter => "invalid character in hash encoding",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+pub(crate) struct Alphabet {
+    chars: &'static [u8],
+    bits: usize,
+    group: usize,
+    pad: bool,
+}
+
+const BASE64_CHARS: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_CHARS: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const BASE32_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE32_LOWER_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+const BASE64: Alphabet = Alphabet { chars: BASE64_CHARS, bits: 6, group: 4, pad: true };
+const BASE32: Alphabet = Alphabet { chars: BASE32_CHARS, bits: 5, group: 8, pad: true };
+
+// Unpadded alphabets, for multibase.
+pub(crate) const BASE64_NOPAD: Alphabet =
+    Alphabet { chars: BASE64_CHARS, bits: 6, group: 4, pad: false };
+pub(crate) const BASE64URL_NOPAD: Alphabet =
+    Alphabet { chars: BASE64URL_CHARS, bits: 6, group: 4, pad: false };
+pub(crate) const BASE32_NOPAD: Alphabet =
+    Alphabet { chars: BASE32_CHARS, bits: 5, group: 8, pad: false };
+pub(crate) const BASE32_LOWER_NOPAD: Alphabet =
+    Alphabet { chars: BASE32_LOWER_CHARS, bits: 5, group: 8, pad: false };
+
+impl Alphabet {
+    pub(crate) fn encode<W: fmt::Write>(&self, bytes: &[u8], f: &mut W) -> fmt::Result {
+        let mask = (1 << self.bits) - 1;
+        let mut acc = 0;
+        let mut nbits = 0;
+        for &byte in bytes {
+            acc = (acc << 8) | byte as usize;
+            nbits += 8;
+            while nbits >= self.bits {
+                nbits -= self.bits;
+                f.write_char(self.chars[(acc >> nbits) & mask] as char)?;
+            }
+        }
+        if nbits > 0 {
+            f.write_char(self.chars[(acc << (self.bits - nbits)) & mask] as char)?;
+        }
+
+        if self.pad {
+            let len = (bytes.len() * 8).div_ceil(self.bits);
+            for _ in len..len.next_multiple_of(self.group) {
+                f.write_char('=')?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decode(&self, s: &str, buf: &mut [u8]) -> Result<usize, Error> {
+        let s = s.as_bytes();
+        let data = if self.pad {
+            if s.len() % self.group != 0 {
+                return Err(Error::InvalidLength);
+            }
+            match s.iter().rposition(|&c| c != b'=') {
+                Some(end) => &s[..end + 1],
+                None => return Err(Error::InvalidLength),
+            }
+        } else {
+            s
+        };
+
+        let mut acc = 0;
+        let mut nbits = 0;
+        let mut len = 0;
+        for &c in data {
+            let value = self.chars.iter().position(|&x| x == c)
+                .ok_or(Error::InvalidCharacter)?;
+            acc = (acc << self.bits) | value;
+            nbits += self.bits;
+            if nbits >= 8 {
+                nbits -= 8;
+                *buf.get_mut(len).ok_or(Error::InvalidLength)? = (acc >> nbits) as u8;
+                len += 1;
+            }
+            acc &= (1 << nbits) - 1;
+        }
+
+        if acc != 0 {
+            return Err(Error::InvalidCharacter);
+        }
+        if data.len() != (len * 8).div_ceil(self.bits)
+            || (self.pad && s.len() != data.len().next_multiple_of(self.group)) {
+            return Err(Error::InvalidLength);
+        }
+        Ok(len)
+    }
+}
+
+/// Hash result formatted as base64.
+#[derive(Clone, Copy, Debug)]
+pub struct Base64<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> fmt::Display for Base64<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        BASE64.encode(self.bytes, f)
+    }
+}
+
+/// Hash result formatted as base32.
+#[derive(Clone, Copy, Debug)]
+pub struct Base32<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> fmt::Display for Base32<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        BASE32.encode(self.bytes, f)
+    }
+}
+
+pub(crate) fn base64(bytes: &[u8]) -> Base64<'_> {
+    Base64 { bytes }
+}
+
+pub(crate) fn base32(bytes: &[u8]) -> Base32<'_> {
+    Base32 { bytes }
+}
+
+pub(crate) fn decode_base64(s: &str, buf: &mut [u8]) -> Result<usize, Error> {
+    BASE64.decode(s, buf)
+}
+
+pub(crate) fn decode_base32(s: &str, buf: &mut [u8]) -> Result<usize, Error> {
+    BASE32.decode(s, buf)
+}
+
+pub(crate) fn encode_hex<W: fmt::Write>(bytes: &[u8], upper: bool, f: &mut W)
+                                        -> fmt::Result {
+    let chars = if upper { b"0123456789ABCDEF" } else { b"0123456789abcdef" };
+    for &byte in bytes {
+        f.write_char(chars[(byte >> 4) as usize] as char)?;
+        f.write_char(chars[(byte & 0xF) as usize] as char)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn decode_hex(s: &str, buf: &mut [u8]) -> Result<usize, Error> {
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 || s.len() / 2 > buf.len() {
+        return Err(Error::InvalidLength);
+    }
+
+    fn digit(c: u8) -> Result<u8, Error> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(Error::InvalidCharacter),
+        }
+    }
+
+    for (byte, pair) in buf.iter_mut().zip(s.chunks(2)) {
+        *byte = (digit(pair[0])? << 4) | digit(pair[1])?;
+    }
+    Ok(s.len() / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, BASE32_LOWER_NOPAD, BASE64URL_NOPAD};
+    use super::{decode_base32, decode_base64, decode_hex};
+
+    #[test]
+    fn test_decode() {
+        let mut buf = [0; 8];
+        assert_eq!(decode_hex("00fFa5", &mut buf), Ok(3));
+        assert_eq!(&buf[..3], &[0x00, 0xFF, 0xA5]);
+        assert_eq!(decode_base64("Zm9vYg==", &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"foob");
+        assert_eq!(decode_base32("MZXW6YQ=", &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"foob");
+        assert_eq!(BASE32_LOWER_NOPAD.decode("mzxw6yq", &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"foob");
+        assert_eq!(BASE64URL_NOPAD.decode("-_8", &mut buf), Ok(2));
+        assert_eq!(&buf[..2], &[0xFB, 0xFF]);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let mut buf = [0; 4];
+        assert_eq!(decode_hex("abc", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_hex("0011223344", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_hex("0g", &mut buf), Err(Error::InvalidCharacter));
+
+        assert_eq!(decode_base64("Zm9vYg", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_base64("Zm9vYh==", &mut buf), Err(Error::InvalidCharacter));
+        assert_eq!(decode_base64("Zm9v====", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_base64("Zm=vYg==", &mut buf), Err(Error::InvalidCharacter));
+        assert_eq!(decode_base64("Zm9vYmFy", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_base64("====", &mut buf), Err(Error::InvalidLength));
+
+        assert_eq!(decode_base32("MZXW6YQ", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(decode_base32("MZXW6YR=", &mut buf), Err(Error::InvalidCharacter));
+        assert_eq!(decode_base32("mzxw6yq=", &mut buf), Err(Error::InvalidCharacter));
+
+        assert_eq!(BASE32_LOWER_NOPAD.decode("mzxw6yq=", &mut buf),
+                   Err(Error::InvalidCharacter));
+        assert_eq!(BASE32_LOWER_NOPAD.decode("mzxw6a", &mut buf), Err(Error::InvalidLength));
+        assert_eq!(BASE64URL_NOPAD.decode("Zm9vA", &mut buf), Err(Error::InvalidLength));
+    }
+}
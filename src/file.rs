@@ -0,0 +1,214 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Hashing of files and readers.
+//!
+//! These functions feed a hashing context (or anything else which
+//! implements `io::Write`) with everything read from a reader or a file,
+//! so the context can be created with any key or parameters. Each one
+//! optionally calls a progress callback with the total number of bytes
+//! processed so far. For the common cases, the `hash_reader` and
+//! `hash_file` functions of the `blake2b` and `blake2s` modules, and of
+//! their `Params` builders, create the context and return the resulting
+//! hash.
+//!
+//! With the `mmap` cargo feature, large regular files are memory-mapped
+//! instead of read. The result is undefined if the file is modified by
+//! another process while it is mapped.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2b::Blake2b;
+//! use blake2_rfc::file::update_reader;
+//!
+//! let mut context = Blake2b::with_key(64, b"key");
+//! let mut reader: &[u8] = b"The quick brown fox jumps over the lazy dog";
+//! let len = update_reader(&mut context, &mut reader, None).unwrap();
+//! let hash = context.finalize();
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Size of the buffer used to read from a reader.
+///
+/// This is a multiple of the block size of all the hash functions, so
+/// the data is compressed without being buffered within the context.
+pub const BUF_LEN: usize = 64 * 1024;
+
+/// Minimum length of a file for it to be memory-mapped.
+#[cfg(feature = "mmap")]
+pub const MMAP_MIN_LEN: u64 = 1024 * 1024;
+
+/// Writes everything read from a reader into a hashing context.
+///
+/// Returns the number of bytes read. Reads interrupted by a signal are
+/// retried.
+pub fn update_reader<W, R>(state: &mut W, reader: &mut R,
+                           mut progress: Option<&mut dyn FnMut(u64)>)
+                           -> io::Result<u64>
+    where W: Write + ?Sized, R: Read + ?Sized
+{
+    let mut buf = vec![0; BUF_LEN];
+    let mut total = 0;
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(len) => len,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        state.write_all(&buf[..len])?;
+        total += len as u64;
+        if let Some(ref mut progress) = progress {
+            progress(total);
+        }
+    }
+}
+
+/// Writes the contents of a file into a hashing context.
+///
+/// Returns the length of the file.
+pub fn update_file<W, P>(state: &mut W, path: P,
+                         progress: Option<&mut dyn FnMut(u64)>)
+                         -> io::Result<u64>
+    where W: Write + ?Sized, P: AsRef<Path>
+{
+    let mut file = File::open(path)?;
+
+    #[cfg(feature = "mmap")]
+    {
+        if let Some(map) = map_file(&file)? {
+            return update_slice(state, &map, progress);
+        }
+    }
+
+    update_reader(state, &mut file, progress)
+}
+
+#[cfg(feature = "mmap")]
+fn map_file(file: &File) -> io::Result<Option<::memmap2::Mmap>> {
+    let metadata = file.metadata()?;
+    if !metadata.file_type().is_file() || metadata.len() < MMAP_MIN_LEN {
+        return Ok(None);
+    }
+
+    // A failure to map the file is not an error, since it can still be
+    // read normally.
+    Ok(unsafe { ::memmap2::Mmap::map(file) }.ok())
+}
+
+#[cfg(feature = "mmap")]
+fn update_slice<W>(state: &mut W, data: &[u8],
+                   mut progress: Option<&mut dyn FnMut(u64)>)
+                   -> io::Result<u64>
+    where W: Write + ?Sized
+{
+    // Large steps, so the callback is not called too often.
+    let mut total = 0;
+    for part in data.chunks(BUF_LEN * 16) {
+        state.write_all(part)?;
+        total += part.len() as u64;
+        if let Some(ref mut progress) = progress {
+            progress(total);
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process;
+    use std::vec::Vec;
+
+    use blake2::selftest_seq;
+    use blake2b::{self, Blake2b, blake2b};
+    use blake2s::{self, blake2s};
+    use generichash;
+    use super::{BUF_LEN, update_file, update_reader};
+
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = env::temp_dir().join(format!("blake2-rfc-{}-{}",
+                                                process::id(), name));
+        File::create(&path).unwrap().write_all(data).unwrap();
+        path
+    }
+
+    fn input(len: usize) -> Vec<u8> {
+        selftest_seq(1024).iter().cloned().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn test_reader() {
+        let data = input(3 * BUF_LEN + 5);
+        assert_eq!(blake2b::hash_reader(64, Some(b"key"), &data[..]).unwrap(),
+                   blake2b(64, b"key", &data));
+        assert_eq!(blake2s::hash_reader(32, None, &data[..]).unwrap(),
+                   blake2s(32, &[], &data));
+    }
+
+    #[test]
+    fn test_reader_params() {
+        let data = input(3 * BUF_LEN + 5);
+        let (salt, personal) = ([1; 16], [2; 16]);
+        let hash = blake2b::Params::new(32)
+            .salt(&salt)
+            .personal(&personal)
+            .hash_reader(Some(b"key"), &data[..])
+            .unwrap();
+
+        let mut out = [0; 32];
+        generichash::hash_salt_personal(&mut out, &data, b"key", Some(&salt),
+                                        Some(&personal)).unwrap();
+        assert_eq!(hash.as_bytes(), &out);
+    }
+
+    #[test]
+    fn test_progress() {
+        let data = input(3 * BUF_LEN + 5);
+        let mut calls = Vec::new();
+        let mut state = Blake2b::new(64);
+        let len = update_reader(&mut state, &mut &data[..],
+                                Some(&mut |n| calls.push(n))).unwrap();
+
+        assert_eq!(len, data.len() as u64);
+        assert_eq!(calls, [BUF_LEN as u64, 2 * BUF_LEN as u64,
+                           3 * BUF_LEN as u64, data.len() as u64]);
+        assert_eq!(state.finalize(), blake2b(64, &[], &data));
+    }
+
+    #[test]
+    fn test_file() {
+        for &(name, len) in &[("empty", 0), ("small", 100),
+                              ("large", 2 * 1024 * 1024 + 3)] {
+            let data = input(len);
+            let path = temp_file(name, &data);
+
+            let mut last = 0;
+            let mut state = Blake2b::with_key(32, b"key");
+            update_file(&mut state, &path, Some(&mut |n| last = n)).unwrap();
+            assert_eq!(last, len as u64);
+            assert_eq!(state.finalize(), blake2b(32, b"key", &data));
+
+            assert_eq!(blake2s::hash_file(32, None, &path).unwrap(),
+                       blake2s(32, &[], &data));
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert!(blake2b::hash_file(64, None, "/nonexistent/blake2-rfc").is_err());
+    }
+}
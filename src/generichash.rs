@@ -0,0 +1,203 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Compatibility with `crypto_generichash_blake2b` from libsodium.
+//!
+//! The functions in this module have the same parameters, checks and
+//! results as their libsodium counterparts, to make it easy to port code
+//! which uses libsodium. A `None` salt or personalization is the same as
+//! a `NULL` pointer, and an empty key is the same as no key.
+//!
+//! As in libsodium, the `_MIN` constants are only recommendations: any
+//! output length from 1 to 64 bytes, and any key length up to 64 bytes,
+//! is accepted.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::generichash::{self, State};
+//!
+//! let mut hash = [0; generichash::BYTES];
+//! generichash::hash(&mut hash, b"message", b"").unwrap();
+//!
+//! let mut state = State::init(b"", generichash::BYTES).unwrap();
+//! state.update(b"mes");
+//! state.update(b"sage");
+//! let mut hash2 = [0; generichash::BYTES];
+//! state.finalize(&mut hash2).unwrap();
+//! assert_eq!(hash, hash2);
+//! ```
+
+use core::fmt;
+
+use blake2b::{Blake2b, Params};
+
+/// Minimum recommended output length (`crypto_generichash_BYTES_MIN`).
+pub const BYTES_MIN: usize = 16;
+/// Maximum output length (`crypto_generichash_BYTES_MAX`).
+pub const BYTES_MAX: usize = 64;
+/// Default output length (`crypto_generichash_BYTES`).
+pub const BYTES: usize = 32;
+/// Minimum recommended key length (`crypto_generichash_KEYBYTES_MIN`).
+pub const KEYBYTES_MIN: usize = 16;
+/// Maximum key length (`crypto_generichash_KEYBYTES_MAX`).
+pub const KEYBYTES_MAX: usize = 64;
+/// Default key length (`crypto_generichash_KEYBYTES`).
+pub const KEYBYTES: usize = 32;
+/// Salt length (`crypto_generichash_blake2b_SALTBYTES`).
+pub const SALTBYTES: usize = 16;
+/// Personalization length (`crypto_generichash_blake2b_PERSONALBYTES`).
+pub const PERSONALBYTES: usize = 16;
+
+/// Returns the size of the libsodium state (`crypto_generichash_statebytes`).
+///
+/// This is the size of the opaque state in libsodium, for code which
+/// stores or allocates it; the `State` of this module is not the same
+/// size, and cannot be exchanged with libsodium.
+pub fn statebytes() -> usize {
+    384
+}
+
+/// Error returned where libsodium returns `-1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid generichash parameters")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+/// Computes a hash (`crypto_generichash`).
+pub fn hash(out: &mut [u8], input: &[u8], key: &[u8]) -> Result<(), Error> {
+    hash_salt_personal(out, input, key, None, None)
+}
+
+/// Computes a hash with a salt and a personalization
+/// (`crypto_generichash_blake2b_salt_personal`).
+pub fn hash_salt_personal(out: &mut [u8], input: &[u8], key: &[u8],
+                          salt: Option<&[u8; SALTBYTES]>,
+                          personal: Option<&[u8; PERSONALBYTES]>)
+                          -> Result<(), Error> {
+    let mut state = State::init_salt_personal(key, out.len(), salt, personal)?;
+    state.update(input);
+    state.finalize(out)
+}
+
+/// Hashing state (`crypto_generichash_state`).
+#[derive(Clone, Debug)]
+pub struct State {
+    state: Blake2b,
+}
+
+impl State {
+    /// Initializes a hashing state (`crypto_generichash_init`).
+    pub fn init(key: &[u8], outlen: usize) -> Result<Self, Error> {
+        Self::init_salt_personal(key, outlen, None, None)
+    }
+
+    /// Initializes a hashing state with a salt and a personalization
+    /// (`crypto_generichash_blake2b_init_salt_personal`).
+    pub fn init_salt_personal(key: &[u8], outlen: usize,
+                              salt: Option<&[u8; SALTBYTES]>,
+                              personal: Option<&[u8; PERSONALBYTES]>)
+                              -> Result<Self, Error> {
+        if outlen == 0 || outlen > BYTES_MAX || key.len() > KEYBYTES_MAX {
+            return Err(Error);
+        }
+
+        let params = Params::new(outlen)
+            .salt(salt.map_or(&[][..], |salt| &salt[..]))
+            .personal(personal.map_or(&[][..], |personal| &personal[..]));
+        Ok(State { state: params.to_state_with_output(key, BYTES_MAX) })
+    }
+
+    /// Hashes a piece of data (`crypto_generichash_update`).
+    pub fn update(&mut self, input: &[u8]) {
+        self.state.update(input);
+    }
+
+    /// Writes the resulting hash (`crypto_generichash_final`).
+    ///
+    /// As in libsodium, the length of `out` is not required to be the
+    /// `outlen` given to `init`; the result is then a truncation of the
+    /// 64-byte chaining value, which is not the hash for that length.
+    pub fn finalize(self, out: &mut [u8]) -> Result<(), Error> {
+        if out.is_empty() || out.len() > BYTES_MAX {
+            return Err(Error);
+        }
+        out.copy_from_slice(&self.state.finalize().as_bytes()[..out.len()]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use blake2b::blake2b;
+    use super::{Error, State, hash, hash_salt_personal};
+
+    #[test]
+    fn test_hash() {
+        let input = selftest_seq(300);
+        let key = selftest_seq(64);
+        for &outlen in &[1, 16, 32, 64] {
+            let mut out = [0; 64];
+            hash(&mut out[..outlen], &input, &key[..32]).unwrap();
+            assert_eq!(&blake2b(outlen, &key[..32], &input), &out[..outlen]);
+        }
+    }
+
+    #[test]
+    fn test_salt_personal() {
+        let mut out = [0; 32];
+        hash_salt_personal(&mut out, b"message", b"key",
+                           Some(b"0123456789abcdef"), Some(b"personalization!"))
+            .unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"7BC1BE0A0CB1055FD99A14055FC3A933019BAB0034E3DD14DFAE061CA4F833A6")
+            .unwrap()[..]);
+
+        let mut state = State::init_salt_personal(b"", 16, None,
+                                                  Some(b"personalization!"))
+            .unwrap();
+        state.update(b"mess");
+        state.update(b"age");
+        let mut out = [0; 16];
+        state.finalize(&mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"1479CC03209B6FF1FE66F08009851681")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_final_length() {
+        let mut state = State::init(b"", 32).unwrap();
+        state.update(b"message");
+        let mut out = [0; 64];
+        state.finalize(&mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"2E7836CC18AB1DB2A2E239EBF4043772B3359520198B5FD55443B01A1023A5B07819AC4DCAA06A53404F94767FA3F2DC03FE886AF3AA2D18F3778354E44C0C48")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(State::init(b"", 0).unwrap_err(), Error);
+        assert_eq!(State::init(b"", 65).unwrap_err(), Error);
+        assert_eq!(State::init(&[0; 65], 32).unwrap_err(), Error);
+        assert_eq!(hash(&mut [0; 65], b"", b"").unwrap_err(), Error);
+        assert_eq!(State::init(b"", 32).unwrap().finalize(&mut []).unwrap_err(),
+                   Error);
+    }
+}
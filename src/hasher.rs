@@ -0,0 +1,209 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Keyed BLAKE2s as a `Hasher` for hash tables.
+//!
+//! `Blake2sHasher` computes a keyed BLAKE2s hash with a 64-bit output,
+//! which is a strong pseudorandom function of the key, so an attacker who
+//! doesn't know the key cannot choose keys which collide in a hash table.
+//! With the `getrandom` feature, `RandomBlake2sState` creates hashers with
+//! a random key from the operating system, like the standard `RandomState`.
+//!
+//! The output of `write_usize` and `write_isize` depends on the pointer
+//! width, and the hash of a value can depend on its `Hash` implementation
+//! and the Rust version, so these hashes should not be stored or sent
+//! elsewhere.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::hash::{Hash, Hasher};
+//! use blake2_rfc::hasher::Blake2sHasher;
+//!
+//! let mut hasher = Blake2sHasher::new(b"0123456789abcdef");
+//! "value".hash(&mut hasher);
+//! let hash = hasher.finish();
+//! ```
+
+use core::hash::Hasher;
+
+use blake2s::Blake2s;
+
+/// Length of the key used by `RandomBlake2sState`.
+pub const KEY_LEN: usize = 16;
+
+/// Hasher which computes a keyed BLAKE2s hash.
+#[derive(Clone, Debug)]
+pub struct Blake2sHasher {
+    state: Blake2s,
+}
+
+impl Blake2sHasher {
+    /// Creates a hasher with a key (up to 32 bytes).
+    pub fn new(key: &[u8]) -> Self {
+        Blake2sHasher { state: Blake2s::with_key(8, key) }
+    }
+}
+
+macro_rules! write_int {
+    ($($method:ident($ty:ty)),*) => {
+        $(
+            #[inline]
+            fn $method(&mut self, i: $ty) {
+                self.state.update_short(&i.to_le_bytes());
+            }
+        )*
+    }
+}
+
+impl Hasher for Blake2sHasher {
+    /// Returns the first 8 bytes of the hash, as a little-endian integer.
+    fn finish(&self) -> u64 {
+        let mut hash = [0; 8];
+        hash.copy_from_slice(self.state.clone().finalize().as_bytes());
+        u64::from_le_bytes(hash)
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.state.update(bytes);
+    }
+
+    write_int!(write_u8(u8), write_u16(u16), write_u32(u32), write_u64(u64),
+               write_u128(u128), write_usize(usize),
+               write_i8(i8), write_i16(i16), write_i32(i32), write_i64(i64),
+               write_i128(i128), write_isize(isize));
+}
+
+#[cfg(feature = "getrandom")]
+pub use self::random::RandomBlake2sState;
+
+#[cfg(feature = "getrandom")]
+mod random {
+    use core::fmt;
+    use core::hash::BuildHasher;
+
+    use super::{Blake2sHasher, KEY_LEN};
+
+    /// Builder of hashers with a random key.
+    ///
+    /// Each `RandomBlake2sState` has its own key, read from the random
+    /// number generator of the operating system. It needs the
+    /// `getrandom` cargo feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use blake2_rfc::hasher::RandomBlake2sState;
+    ///
+    /// let mut map = HashMap::with_hasher(RandomBlake2sState::new());
+    /// map.insert("key", "value");
+    /// assert_eq!(map["key"], "value");
+    /// ```
+    #[derive(Clone)]
+    pub struct RandomBlake2sState {
+        key: [u8; KEY_LEN],
+    }
+
+    impl RandomBlake2sState {
+        /// Creates a builder with a new random key.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the random number generator of the operating system
+        /// fails.
+        pub fn new() -> Self {
+            let mut key = [0; KEY_LEN];
+            ::getrandom::fill(&mut key).expect("random number generator failed");
+            RandomBlake2sState { key }
+        }
+    }
+
+    impl Default for RandomBlake2sState {
+        fn default() -> Self { Self::new() }
+    }
+
+    impl fmt::Debug for RandomBlake2sState {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("RandomBlake2sState").finish_non_exhaustive()
+        }
+    }
+
+    impl BuildHasher for RandomBlake2sState {
+        type Hasher = Blake2sHasher;
+
+        fn build_hasher(&self) -> Blake2sHasher {
+            Blake2sHasher::new(&self.key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::hash::Hasher;
+
+    use blake2s::blake2s;
+    use super::Blake2sHasher;
+
+    fn expected(key: &[u8], data: &[u8]) -> u64 {
+        let mut hash = [0; 8];
+        hash.copy_from_slice(blake2s(8, key, data).as_bytes());
+        u64::from_le_bytes(hash)
+    }
+
+    #[test]
+    fn test_write() {
+        let mut hasher = Blake2sHasher::new(b"key");
+        assert_eq!(hasher.finish(), expected(b"key", &[]));
+        hasher.write(b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(hasher.finish(),
+                   expected(b"key", b"The quick brown fox jumps over the lazy dog"));
+    }
+
+    #[test]
+    fn test_write_int() {
+        // Unaligned, and crossing several block boundaries.
+        let mut data = [0; 203];
+        let mut hasher = Blake2sHasher::new(b"key");
+        hasher.write_u8(0xA5);
+        data[0] = 0xA5;
+        for i in 0..25u64 {
+            let x = i.wrapping_mul(0x9E3779B97F4A7C15);
+            hasher.write_u64(x);
+            data[1 + 8 * i as usize..][..8].copy_from_slice(&x.to_le_bytes());
+            assert_eq!(hasher.finish(), expected(b"key", &data[..9 + 8 * i as usize]));
+        }
+        hasher.write_i16(-2);
+        data[201..].copy_from_slice(&(-2i16).to_le_bytes());
+        assert_eq!(hasher.finish(), expected(b"key", &data));
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn test_random_state() {
+        use core::hash::{BuildHasher, Hash};
+        use std::collections::HashMap;
+        use super::RandomBlake2sState;
+
+        let a = RandomBlake2sState::new();
+        let b = RandomBlake2sState::new();
+        let hash = |state: &RandomBlake2sState| {
+            let mut hasher = state.build_hasher();
+            "value".hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&a), hash(&a.clone()));
+        assert_ne!(hash(&a), hash(&b));
+
+        let mut map = HashMap::with_hasher(a);
+        for i in 0..1000 {
+            map.insert(i, i * 2);
+        }
+        assert_eq!(map[&500], 1000);
+    }
+}
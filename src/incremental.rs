@@ -0,0 +1,228 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Incremental tree hashing of modified data.
+//!
+//! A `TreeCache` keeps the digest of every node of the tree hash of
+//! some data (see the `tree` module). After a range of the data is
+//! modified, only the leaves covering that range, and the parent nodes
+//! above them, have to be hashed again.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::incremental::TreeCache;
+//! use blake2_rfc::tree;
+//!
+//! let mut data = vec![0; 100000];
+//! let mut cache = TreeCache::new(&data);
+//!
+//! data[50000..50010].copy_from_slice(b"0123456789");
+//! cache.update(&data, 50000..50010);
+//! assert_eq!(cache.root(), tree::hash(&data));
+//! ```
+
+use std::cmp;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::vec::Vec;
+
+use blake2b::Blake2bResult;
+use tree::{self, CHUNK_LEN, HASH_LEN};
+use tree::{chunk_count, content_range, level_count, root_depth};
+
+/// Cache of the node digests of a tree hash.
+#[derive(Clone, Debug)]
+pub struct TreeCache {
+    len: u64,
+    levels: Vec<Vec<[u8; HASH_LEN]>>,
+}
+
+impl TreeCache {
+    /// Creates a cache by hashing the whole data.
+    pub fn new(data: &[u8]) -> Self {
+        let mut cache = TreeCache { len: 0, levels: Vec::new() };
+        cache.rehash(data, 0, chunk_count(data.len() as u64));
+        cache
+    }
+
+    /// Updates the cache after a range of the data was modified.
+    ///
+    /// The `data` must be the whole data after the modification. If its
+    /// length changed, everything after the old or the new end of the
+    /// data (whichever comes first) is also treated as modified.
+    pub fn update(&mut self, data: &[u8], modified: Range<u64>) {
+        let len = data.len() as u64;
+        let chunks = chunk_count(len);
+        let chunk = CHUNK_LEN as u64;
+
+        let mut first = modified.start / chunk;
+        let mut end = cmp::min((modified.end + chunk - 1) / chunk, chunks);
+        if len != self.len {
+            first = cmp::min(first, chunk_count(cmp::min(len, self.len)) - 1);
+            end = chunks;
+        }
+        self.rehash(data, cmp::min(first, end), end);
+    }
+
+    /// Returns the root hash, which is the same as `tree::hash`.
+    pub fn root(&self) -> Blake2bResult {
+        Blake2bResult::from_bytes(&self.levels[self.levels.len() - 1][0])
+    }
+
+    /// Returns the length of the data.
+    pub fn content_len(&self) -> u64 {
+        self.len
+    }
+
+    /// Hashes again the leaves from `first` to `end`, and their parents.
+    fn rehash(&mut self, data: &[u8], mut first: u64, mut end: u64) {
+        let len = data.len() as u64;
+        let chunks = chunk_count(len);
+        let depth = root_depth(chunks);
+
+        self.len = len;
+        self.levels.resize(depth as usize + 1, Vec::new());
+        for (level, nodes) in self.levels.iter_mut().enumerate() {
+            nodes.resize(level_count(chunks, level as u32) as usize,
+                         [0; HASH_LEN]);
+        }
+
+        for offset in first..end {
+            let range = content_range(len, 0, offset);
+            let mut state = tree::leaf(offset);
+            state.update(&data[range.start as usize..range.end as usize]);
+            let hash = tree::finalize(state, chunks, 0, offset);
+            self.levels[0][offset as usize].copy_from_slice(hash.as_bytes());
+        }
+
+        for level in 1..depth + 1 {
+            first >>= 1;
+            end = (end + 1) >> 1;
+
+            let (below, above) = self.levels.split_at_mut(level as usize);
+            let (children, nodes) = (&below[level as usize - 1], &mut above[0]);
+            for offset in first..end {
+                let mut state = tree::parent(level, offset);
+                for child in tree::children(chunks, level, offset) {
+                    state.update(&children[child as usize]);
+                }
+                let hash = tree::finalize(state, chunks, level, offset);
+                nodes[offset as usize].copy_from_slice(hash.as_bytes());
+            }
+        }
+    }
+
+    /// Serializes the cache.
+    ///
+    /// The format is the length of the data, as a little-endian 64-bit
+    /// integer, followed by the digests of all the nodes, level by level
+    /// starting from the leaves.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.len.to_le_bytes())?;
+        for nodes in &self.levels {
+            for hash in nodes {
+                w.write_all(hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserializes a cache written by `write_to`.
+    ///
+    /// The digests are not verified: a cache read from untrusted storage
+    /// can result in a wrong root hash.
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut header = [0; 8];
+        r.read_exact(&mut header)?;
+
+        let len = u64::from_le_bytes(header);
+        let chunks = chunk_count(len);
+
+        let mut levels = Vec::new();
+        for level in 0..root_depth(chunks) + 1 {
+            let mut nodes = Vec::new();
+            for _ in 0..level_count(chunks, level) {
+                let mut hash = [0; HASH_LEN];
+                r.read_exact(&mut hash)?;
+                nodes.push(hash);
+            }
+            levels.push(nodes);
+        }
+        Ok(TreeCache { len, levels })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use tree::{self, CHUNK_LEN};
+    use super::TreeCache;
+
+    fn input(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(seed) ^ (i >> 8) as u8).collect()
+    }
+
+    #[test]
+    fn test_new() {
+        for &len in &[0, 1, CHUNK_LEN, CHUNK_LEN + 1, 9 * CHUNK_LEN - 3] {
+            let data = input(len, 3);
+            assert_eq!(TreeCache::new(&data).root(), tree::hash(&data));
+        }
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut data = input(13 * CHUNK_LEN + 5, 3);
+        let mut cache = TreeCache::new(&data);
+
+        for &(start, end) in &[(0, 1), (CHUNK_LEN - 1, CHUNK_LEN + 1),
+                               (5 * CHUNK_LEN, 8 * CHUNK_LEN + 2),
+                               (13 * CHUNK_LEN, 13 * CHUNK_LEN + 5),
+                               (100, 100)] {
+            for b in &mut data[start..end] {
+                *b = !*b;
+            }
+            cache.update(&data, start as u64..end as u64);
+            assert_eq!(cache.root(), tree::hash(&data));
+        }
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut data = input(3 * CHUNK_LEN, 5);
+        let mut cache = TreeCache::new(&data);
+
+        for &len in &[3 * CHUNK_LEN + 1, 9 * CHUNK_LEN, 2 * CHUNK_LEN - 7,
+                      0, CHUNK_LEN, 17 * CHUNK_LEN + 1] {
+            let old = data.len();
+            data.resize(len, 0x5A);
+            cache.update(&data, old as u64..len as u64);
+            assert_eq!(cache.root(), tree::hash(&data));
+            assert_eq!(cache.content_len(), len as u64);
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let data = input(7 * CHUNK_LEN + 100, 7);
+        let cache = TreeCache::new(&data);
+
+        let mut bytes = Vec::new();
+        cache.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + (8 + 4 + 2 + 1) * 64);
+
+        let mut restored = TreeCache::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(restored.root(), cache.root());
+
+        let mut data = data;
+        data[0] = !data[0];
+        restored.update(&data, 0..1);
+        assert_eq!(restored.root(), tree::hash(&data));
+    }
+}
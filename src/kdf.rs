@@ -0,0 +1,189 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Key derivation with BLAKE2b.
+//!
+//! `derive_from_key` derives subkeys from a master key, a context and a
+//! subkey ID, in the same way as `crypto_kdf_derive_from_key` from
+//! libsodium: the master key is the BLAKE2b key, the subkey ID (as a
+//! little-endian 64-bit integer) is the salt, and the context is the
+//! personalization, with an empty input.
+//!
+//! `derive_key` derives a key from key material of any length and a
+//! context string, which should be unique to the application and the
+//! purpose of the key. The context string is first hashed into a 16-byte
+//! value with BLAKE2b (personalized with `blake2-rfc kdf`), which is used
+//! as the personalization when hashing the key material.
+//!
+//! For outputs up to 64 bytes, the derived key is the BLAKE2b hash with
+//! the output length as the digest length. Longer outputs use BLAKE2Xb
+//! with the same parameters, so they are not a prefix or an extension of
+//! a shorter output.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::kdf;
+//!
+//! let master_key = [0x42; kdf::KEY_LEN];
+//! let mut subkey = [0; 32];
+//! kdf::derive_from_key(&mut subkey, 1, b"Examples", &master_key);
+//!
+//! let mut key = [0; 100];
+//! kdf::derive_key_into("example.com 2026 session keys", b"secret", &mut key);
+//! ```
+
+use blake2b::{Blake2b, Params};
+
+/// Length of the master key of `derive_from_key`.
+pub const KEY_LEN: usize = 32;
+
+/// Length of the context of `derive_from_key`.
+pub const CONTEXT_LEN: usize = 8;
+
+/// Minimum length of a subkey derived by `derive_from_key`.
+pub const SUBKEY_MIN_LEN: usize = 16;
+
+/// Personalization used to hash the context string of `derive_key`.
+const CONTEXT_PERSONAL: &[u8] = b"blake2-rfc kdf";
+
+/// Derives a subkey from a master key, in a way compatible with libsodium.
+///
+/// Subkeys up to 64 bytes are the same as `crypto_kdf_derive_from_key`;
+/// longer subkeys (which libsodium doesn't support) use BLAKE2Xb.
+///
+/// # Panics
+///
+/// Panics if the subkey is shorter than `SUBKEY_MIN_LEN` bytes.
+pub fn derive_from_key(subkey: &mut [u8], subkey_id: u64,
+                       context: &[u8; CONTEXT_LEN], key: &[u8; KEY_LEN]) {
+    assert!(subkey.len() >= SUBKEY_MIN_LEN);
+    let salt = subkey_id.to_le_bytes();
+    blake2x(subkey, key, &salt, context, &[]);
+}
+
+/// Derives a key from key material and a context string.
+pub fn derive_key_into(context: &str, key_material: &[u8], out: &mut [u8]) {
+    let mut personal = Params::new(16).personal(CONTEXT_PERSONAL).to_state(None);
+    personal.update(context.as_bytes());
+    blake2x(out, &[], &[], personal.finalize().as_bytes(), key_material);
+}
+
+/// Derives a key of `out_len` bytes from key material and a context string.
+#[cfg(feature = "std")]
+pub fn derive_key(context: &str, key_material: &[u8], out_len: usize)
+                  -> ::std::vec::Vec<u8> {
+    let mut out = vec![0; out_len];
+    derive_key_into(context, key_material, &mut out);
+    out
+}
+
+/// Hashes the input with BLAKE2b if the output fits in a single hash,
+/// otherwise with BLAKE2Xb.
+pub(crate) fn blake2x(out: &mut [u8], k: &[u8], salt: &[u8], personal: &[u8],
+                      data: &[u8]) {
+    assert!(!out.is_empty() && out.len() < u32::MAX as usize);
+    if out.len() <= 64 {
+        let mut state = Params::new(out.len())
+            .salt(salt)
+            .personal(personal)
+            .to_state(Some(k));
+        state.update(data);
+        out.copy_from_slice(state.finalize().as_bytes());
+        return;
+    }
+
+    let xof_len = out.len() as u32;
+    let mut root = Params::new(64)
+        .salt(salt)
+        .personal(personal)
+        .xof_length(xof_len)
+        .to_state(Some(k));
+    root.update(data);
+    let root = root.finalize();
+
+    for (i, part) in out.chunks_mut(64).enumerate() {
+        let mut state: Blake2b = Params::new(part.len())
+            .fanout(0)
+            .max_depth(0)
+            .leaf_length(64)
+            .node_offset(i as u64)
+            .xof_length(xof_len)
+            .inner_length(64)
+            .salt(salt)
+            .personal(personal)
+            .to_state(None);
+        state.update(root.as_bytes());
+        part.copy_from_slice(state.finalize().as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use super::{derive_from_key, derive_key_into};
+
+    const KEY: [u8; 32] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+        0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F,
+    ];
+
+    #[test]
+    fn test_derive_from_key() {
+        let mut subkey = [0; 32];
+        derive_from_key(&mut subkey, 0, b"Examples", &KEY);
+        assert_eq!(&subkey[..], &HEXUPPER.decode(
+            b"D676D6D54480F13ED75C930629F21919BF7126656E4B7F9EF045EE34AC288161")
+            .unwrap()[..]);
+
+        let mut subkey = [0; 64];
+        derive_from_key(&mut subkey, 1, b"Examples", &KEY);
+        assert_eq!(&subkey[..], &HEXUPPER.decode(
+            b"D8E65AE005F978356A20CA33D8185BA9056F0B8766310CFB5FF0209B256FD834709EC4DC9E398E2F3C5AC9ECAA69BDF1EAC6C3EC228B12A9B203247F18F7D708")
+            .unwrap()[..]);
+
+        let mut subkey = [0; 16];
+        derive_from_key(&mut subkey, 0xFEDCBA9876543210, b"Examples", &KEY);
+        assert_eq!(&subkey[..], &HEXUPPER.decode(
+            b"EC97C32E3B1263B02178489163585717")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_derive_from_key_long() {
+        let mut subkey = [0; 100];
+        derive_from_key(&mut subkey, 7, b"Examples", &KEY);
+        assert_eq!(&subkey[..], &HEXUPPER.decode(
+            b"5991B7FF9AF500933C1C9BE3B2B813573B3077548E2447CDA6CC8ACF9D63FC57C7DACFD1935E9E7E8EBA0A1ABB36F9D542AFD54E5A14B2605F8B8AE874DB3F2F48989F0C74C45C78F8E5C723F853C3A06ABD0C6B4C28F5B7E28C6DC7F92417FB62A7418A")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_derive_key() {
+        let mut key = [0; 32];
+        derive_key_into("example.com 2026 session keys", b"master secret", &mut key);
+        assert_eq!(&key[..], &HEXUPPER.decode(
+            b"DFC7464538F20E332680CBC3996D552871E95DF9C345D29E18F678CDBB1E14C5")
+            .unwrap()[..]);
+
+        let mut key = [0; 130];
+        derive_key_into("example.com 2026 session keys", b"master secret", &mut key);
+        assert_eq!(&key[..], &HEXUPPER.decode(
+            b"8A309E46C253D36803C2EABCA0F039E12F598140BF023FCF712ECDD6D60F2BAAF91742853B31388BBFE80DE64E09F82DFC1C33C8D2C0A4434356F208C912E418D6440C8BCA19DE531A902C98E50E5D979FFAB83CDBE4B04E7F50A204E300862F844D469D60FCC015A8A605BE30D910B2EFACCF75DE4D39525303299AD3E6AFAC3728")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_short_subkey() {
+        derive_from_key(&mut [0; 15], 0, b"Examples", &KEY);
+    }
+}
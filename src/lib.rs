@@ -24,6 +24,22 @@ extern crate std;
 
 extern crate arrayvec;
 extern crate constant_time_eq;
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
+#[cfg(feature = "getrandom")]
+extern crate getrandom;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "rand_core")]
+extern crate rand_core;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
 
 mod as_bytes;
 mod bytes;
@@ -36,9 +52,44 @@ mod simd;
 
 #[macro_use]
 mod blake2;
+#[macro_use]
+mod tuple;
+#[macro_use]
+mod blake;
+#[cfg(feature = "std")]
+#[macro_use]
+mod tee;
 
 pub mod blake2b;
 pub mod blake2s;
+pub mod blake2bp;
+pub mod blake2sp;
+pub mod blake256;
+pub mod blake512;
+pub mod blake3;
+pub mod hasher;
+pub mod encoding;
+pub mod multihash;
+pub mod generichash;
+pub mod kdf;
+pub mod drbg;
+pub mod transcript;
+pub mod xmd;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "serde")]
+pub mod canonical;
+
+#[cfg(feature = "std")]
+pub mod file;
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+pub mod async_io;
+
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod incremental;
+#[cfg(feature = "std")]
+pub mod verified;
 
 /// Runs the self-test for both BLAKE2b and BLAKE2s.
 #[cold]
@@ -0,0 +1,504 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Multihash and CID encoding of hash results.
+//!
+//! A multihash is a digest prefixed with the unsigned varints of its
+//! hash function code and its length. The BLAKE2 codes encode the output
+//! length: `0xb201` to `0xb240` are BLAKE2b-8 to BLAKE2b-512, and
+//! `0xb241` to `0xb260` are BLAKE2s-8 to BLAKE2s-256. Converting a
+//! multihash back to a hash result checks that its code matches the
+//! length of the digest.
+//!
+//! A version 1 CID is the varint `1`, the varint of the content codec
+//! (such as `RAW` or `DAG_CBOR`) and a multihash. It formats in
+//! multibase, by default as unpadded lowercase base32 with a `b` prefix;
+//! parsing also accepts uppercase base32 (`B`), base16 (`f` and `F`),
+//! base64 (`m`) and base64url (`u`).
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::blake2b::Blake2bResult;
+//! use blake2_rfc::multihash::{self, Cid, RAW};
+//!
+//! let multihash = multihash::blake2b(32, b"hello");
+//! assert_eq!(multihash.code(), 0xb220);
+//!
+//! let cid = Cid::new(RAW, &multihash);
+//! let parsed: Cid = cid.to_string().parse().unwrap();
+//! let hash = Blake2bResult::from_cid(&parsed).unwrap();
+//! assert_eq!(hash.as_bytes(), multihash.digest());
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+
+use blake2b::Blake2bResult;
+use blake2s::Blake2sResult;
+use constant_time_eq::constant_time_eq;
+use encoding;
+
+/// Multicodec of raw binary content.
+pub const RAW: u64 = 0x55;
+
+/// Multicodec of MerkleDAG protobuf content.
+pub const DAG_PB: u64 = 0x70;
+
+/// Multicodec of MerkleDAG CBOR content.
+pub const DAG_CBOR: u64 = 0x71;
+
+/// Code of BLAKE2b with a zero-byte output; BLAKE2b-8 is the next one.
+const BLAKE2B_BASE: u64 = 0xb200;
+
+/// Code of BLAKE2s with a zero-byte output; BLAKE2s-8 is the next one.
+const BLAKE2S_BASE: u64 = 0xb240;
+
+/// Longest varint allowed by the multiformats specification.
+const MAX_VARINT_LEN: usize = 9;
+
+/// Longest digest which fits in a `Multihash`.
+const MAX_DIGEST_LEN: usize = 64;
+
+/// Longest multihash: code, length and digest.
+const MAX_MULTIHASH_LEN: usize = MAX_VARINT_LEN + 1 + MAX_DIGEST_LEN;
+
+/// Longest CID: version, codec and multihash.
+const MAX_CID_LEN: usize = 1 + MAX_VARINT_LEN + MAX_MULTIHASH_LEN;
+
+/// Error returned when decoding a multihash or a CID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A varint is truncated, too long or not minimally encoded.
+    InvalidVarint,
+    /// The digest is truncated, too long, or followed by trailing bytes.
+    InvalidLength,
+    /// The code is not a code of the expected hash function.
+    UnsupportedCode,
+    /// The code doesn't match the length of the digest.
+    LengthMismatch,
+    /// The CID is not a version 1 CID.
+    UnsupportedVersion,
+    /// The multibase prefix is missing or unknown.
+    UnsupportedBase,
+    /// The multibase text is invalid.
+    Encoding(encoding::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidVarint => f.write_str("invalid varint"),
+            Error::InvalidLength => f.write_str("invalid multihash length"),
+            Error::UnsupportedCode => f.write_str("unsupported multihash code"),
+            Error::LengthMismatch => f.write_str("multihash code doesn't match the digest length"),
+            Error::UnsupportedVersion => f.write_str("unsupported CID version"),
+            Error::UnsupportedBase => f.write_str("unsupported multibase"),
+            Error::Encoding(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+impl From<encoding::Error> for Error {
+    fn from(e: encoding::Error) -> Self {
+        Error::Encoding(e)
+    }
+}
+
+fn write_varint(mut x: u64, out: &mut [u8]) -> usize {
+    assert!(x < 1 << 63, "varint too large");
+    let mut len = 0;
+    loop {
+        let byte = (x & 0x7F) as u8;
+        x >>= 7;
+        if x == 0 {
+            out[len] = byte;
+            return len + 1;
+        }
+        out[len] = byte | 0x80;
+        len += 1;
+    }
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, usize), Error> {
+    let mut x = 0;
+    for (i, &byte) in bytes.iter().take(MAX_VARINT_LEN).enumerate() {
+        x |= u64::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            if byte == 0 && i > 0 {
+                return Err(Error::InvalidVarint);
+            }
+            return Ok((x, i + 1));
+        }
+    }
+    Err(Error::InvalidVarint)
+}
+
+/// Self-describing digest.
+///
+/// This container uses a constant-time comparison for equality.
+#[derive(Clone, Copy)]
+pub struct Multihash {
+    buf: [u8; MAX_MULTIHASH_LEN],
+    len: usize,
+    code: u64,
+    digest_at: usize,
+}
+
+impl Multihash {
+    /// Creates a multihash from a code and a digest of up to 64 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the digest is longer than 64 bytes, or if the code is
+    /// 2<sup>63</sup> or more, which doesn't fit in a 9-byte varint.
+    pub fn new(code: u64, digest: &[u8]) -> Self {
+        assert!(digest.len() <= MAX_DIGEST_LEN);
+        let mut buf = [0; MAX_MULTIHASH_LEN];
+        let mut at = write_varint(code, &mut buf);
+        at += write_varint(digest.len() as u64, &mut buf[at..]);
+        buf[at..at + digest.len()].copy_from_slice(digest);
+        Multihash { buf, len: at + digest.len(), code, digest_at: at }
+    }
+
+    /// Decodes a multihash, which must span all of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (multihash, len) = Self::read(bytes)?;
+        if len != bytes.len() {
+            return Err(Error::InvalidLength);
+        }
+        Ok(multihash)
+    }
+
+    /// Decodes a multihash at the start of `bytes`, and returns it with
+    /// its length.
+    fn read(bytes: &[u8]) -> Result<(Self, usize), Error> {
+        let (code, code_len) = read_varint(bytes)?;
+        let (len, len_len) = read_varint(&bytes[code_len..])?;
+        let at = code_len + len_len;
+        if len > MAX_DIGEST_LEN as u64 || bytes.len() - at < len as usize {
+            return Err(Error::InvalidLength);
+        }
+        let multihash = Multihash::new(code, &bytes[at..at + len as usize]);
+        Ok((multihash, at + len as usize))
+    }
+
+    /// Returns the hash function code.
+    #[inline]
+    pub fn code(&self) -> u64 { self.code }
+
+    /// Returns the digest.
+    #[inline]
+    pub fn digest(&self) -> &[u8] { &self.buf[self.digest_at..self.len] }
+
+    /// Returns the encoded multihash.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] { &self.buf[..self.len] }
+
+    /// Returns the digest of a BLAKE2 multihash, after checking that the
+    /// code is in the range after `base` and matches the digest length.
+    fn blake2_digest(&self, base: u64, max_len: usize) -> Result<&[u8], Error> {
+        if self.code <= base || self.code > base + max_len as u64 {
+            return Err(Error::UnsupportedCode);
+        }
+        if self.code - base != self.digest().len() as u64 {
+            return Err(Error::LengthMismatch);
+        }
+        Ok(self.digest())
+    }
+}
+
+impl AsRef<[u8]> for Multihash {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { self.as_bytes() }
+}
+
+impl Eq for Multihash { }
+
+impl PartialEq for Multihash {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl fmt::Display for Multihash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        encoding::encode_hex(self.as_bytes(), false, f)
+    }
+}
+
+impl fmt::Debug for Multihash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Multihash({})", self)
+    }
+}
+
+/// Multibase encoding of a CID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Base {
+    /// Lowercase base16, with the `f` prefix.
+    Base16,
+    /// Lowercase unpadded base32, with the `b` prefix.
+    Base32,
+    /// Unpadded base64, with the `m` prefix.
+    Base64,
+    /// Unpadded URL-safe base64, with the `u` prefix.
+    Base64Url,
+}
+
+/// Version 1 content identifier.
+///
+/// Equality ignores the multibase the CID formats in.
+#[derive(Clone, Copy)]
+pub struct Cid {
+    buf: [u8; MAX_CID_LEN],
+    len: usize,
+    codec: u64,
+    multihash: Multihash,
+    base: Base,
+}
+
+impl Cid {
+    /// Creates a CID from a content codec and a multihash, which formats
+    /// in base32.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the codec is 2<sup>63</sup> or more, which doesn't fit
+    /// in a 9-byte varint.
+    pub fn new(codec: u64, multihash: &Multihash) -> Self {
+        let mut buf = [0; MAX_CID_LEN];
+        let mut len = write_varint(1, &mut buf);
+        len += write_varint(codec, &mut buf[len..]);
+        buf[len..len + multihash.len].copy_from_slice(multihash.as_bytes());
+        len += multihash.len;
+        Cid { buf, len, codec, multihash: *multihash, base: Base::Base32 }
+    }
+
+    /// Decodes a binary CID, which must span all of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (version, version_len) = read_varint(bytes)?;
+        if version != 1 {
+            return Err(Error::UnsupportedVersion);
+        }
+        let (codec, codec_len) = read_varint(&bytes[version_len..])?;
+        let multihash = Multihash::from_bytes(&bytes[version_len + codec_len..])?;
+        Ok(Cid::new(codec, &multihash))
+    }
+
+    /// Sets the multibase the CID formats in.
+    pub fn with_base(mut self, base: Base) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Returns the content codec.
+    #[inline]
+    pub fn codec(&self) -> u64 { self.codec }
+
+    /// Returns the multihash.
+    #[inline]
+    pub fn multihash(&self) -> &Multihash { &self.multihash }
+
+    /// Returns the multibase the CID formats in.
+    #[inline]
+    pub fn base(&self) -> Base { self.base }
+
+    /// Returns the binary CID.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] { &self.buf[..self.len] }
+}
+
+impl Eq for Cid { }
+
+impl PartialEq for Cid {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self.as_bytes();
+        match self.base {
+            Base::Base16 => {
+                f.write_str("f")?;
+                encoding::encode_hex(bytes, false, f)
+            }
+            Base::Base32 => {
+                f.write_str("b")?;
+                encoding::BASE32_LOWER_NOPAD.encode(bytes, f)
+            }
+            Base::Base64 => {
+                f.write_str("m")?;
+                encoding::BASE64_NOPAD.encode(bytes, f)
+            }
+            Base::Base64Url => {
+                f.write_str("u")?;
+                encoding::BASE64URL_NOPAD.encode(bytes, f)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cid({})", self)
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut buf = [0; MAX_CID_LEN];
+        let (base, len) = match s.as_bytes().first() {
+            Some(b'f') | Some(b'F') => (Base::Base16, encoding::decode_hex(&s[1..], &mut buf)?),
+            Some(b'b') => (Base::Base32, encoding::BASE32_LOWER_NOPAD.decode(&s[1..], &mut buf)?),
+            Some(b'B') => (Base::Base32, encoding::BASE32_NOPAD.decode(&s[1..], &mut buf)?),
+            Some(b'm') => (Base::Base64, encoding::BASE64_NOPAD.decode(&s[1..], &mut buf)?),
+            Some(b'u') => (Base::Base64Url, encoding::BASE64URL_NOPAD.decode(&s[1..], &mut buf)?),
+            _ => return Err(Error::UnsupportedBase),
+        };
+        Ok(Cid::from_bytes(&buf[..len])?.with_base(base))
+    }
+}
+
+macro_rules! multihash_impl {
+    ($result:ident, $func:ident, $base:expr, $bytes:expr) => {
+        impl $result {
+            /// Returns the multihash code of the hash result, which
+            /// depends on its length.
+            #[inline]
+            pub fn multihash_code(&self) -> u64 { $base + self.len() as u64 }
+
+            /// Encodes the hash result as a multihash.
+            pub fn to_multihash(&self) -> Multihash {
+                Multihash::new(self.multihash_code(), self.as_bytes())
+            }
+
+            /// Decodes a hash result from a multihash, checking that its
+            /// code matches the length of the digest.
+            pub fn from_multihash(multihash: &Multihash) -> Result<Self, Error> {
+                multihash.blake2_digest($base, $bytes).map($result::from_bytes)
+            }
+
+            /// Encodes the hash result as a CID of content in `codec`.
+            pub fn to_cid(&self, codec: u64) -> Cid {
+                Cid::new(codec, &self.to_multihash())
+            }
+
+            /// Decodes a hash result from the multihash of a CID.
+            pub fn from_cid(cid: &Cid) -> Result<Self, Error> {
+                Self::from_multihash(cid.multihash())
+            }
+        }
+
+        #[doc = concat!("Hashes `data` with ", stringify!($func),
+                        " to a multihash of an `nn`-byte digest.")]
+        pub fn $func(nn: usize, data: &[u8]) -> Multihash {
+            ::$func::$func(nn, &[], data).to_multihash()
+        }
+    }
+}
+
+multihash_impl!(Blake2bResult, blake2b, BLAKE2B_BASE, 64);
+multihash_impl!(Blake2sResult, blake2s, BLAKE2S_BASE, 32);
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXLOWER;
+
+    #[cfg(feature = "std")]
+    use std::string::ToString;
+
+    use blake2b::{Blake2bResult, blake2b};
+    use blake2s::Blake2sResult;
+    use super::{Base, Cid, Error, Multihash, DAG_PB, RAW};
+
+    const HELLO_B256: &str =
+        "a0e40220324dcf027dd4a30a932c441f365a25e86b173defa4b8e58948253471b81b72cf";
+
+    #[test]
+    fn test_multihash() {
+        let multihash = super::blake2b(32, b"hello");
+        assert_eq!(multihash.code(), 0xb220);
+        assert_eq!(multihash.as_bytes(), &HEXLOWER.decode(HELLO_B256.as_bytes()).unwrap()[..]);
+        assert_eq!(Multihash::from_bytes(multihash.as_bytes()), Ok(multihash));
+        assert_eq!(Blake2bResult::from_multihash(&multihash),
+                   Ok(blake2b(32, &[], b"hello")));
+
+        let multihash = super::blake2s(16, b"hello");
+        assert_eq!(multihash.as_bytes(), &HEXLOWER.decode(
+            b"d0e4021096d539653dbf841c384b53d5f04658e5").unwrap()[..]);
+
+        let multihash = super::blake2b(64, b"hello");
+        assert_eq!(multihash.as_bytes(), &HEXLOWER.decode(
+            b"c0e40240e4cfa39a3d37be31c59609e807970799caa68a19bfaa15135f165085e01d41a65ba1e1b146aeb6bd0092b49eac214c103ccfa3a365954bbbe52f74a2b3620c94")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_multihash_invalid() {
+        let bytes = HEXLOWER.decode(HELLO_B256.as_bytes()).unwrap();
+        assert_eq!(Multihash::from_bytes(&bytes[..35]), Err(Error::InvalidLength));
+        assert_eq!(Multihash::from_bytes(&[0xa0, 0xe4]), Err(Error::InvalidVarint));
+        assert_eq!(Multihash::from_bytes(&[0xa0, 0xe4, 0x82, 0x00, 0x00]),
+                   Err(Error::InvalidVarint));
+
+        let multihash = Multihash::new(0xb220, &[0; 16]);
+        assert_eq!(Blake2bResult::from_multihash(&multihash), Err(Error::LengthMismatch));
+        let multihash = super::blake2s(32, b"hello");
+        assert_eq!(Blake2bResult::from_multihash(&multihash), Err(Error::UnsupportedCode));
+        assert!(Blake2sResult::from_multihash(&multihash).is_ok());
+        let multihash = Multihash::new(0x12, &[0; 32]);
+        assert_eq!(Blake2sResult::from_multihash(&multihash), Err(Error::UnsupportedCode));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cid() {
+        let hash = blake2b(32, &[], b"hello");
+        let cid = hash.to_cid(RAW);
+        assert_eq!(cid.to_string(),
+                   "bafk2bzaceaze3tycpxkkgcutfrcb6ns2exugwfz556slrzmjjasti4nydnzm6");
+        assert_eq!(cid.with_base(Base::Base16).to_string(),
+                   "f0155".to_string() + HELLO_B256);
+        assert_eq!(cid.with_base(Base::Base64).to_string(),
+                   "mAVWg5AIgMk3PAn3UowqTLEQfNlol6GsXPe+kuOWJSCU0cbgbcs8");
+
+        for s in &["bafk2bzaceaze3tycpxkkgcutfrcb6ns2exugwfz556slrzmjjasti4nydnzm6",
+                   "BAFK2BZACEAZE3TYCPXKKGCUTFRCB6NS2EXUGWFZ556SLRZMJJASTI4NYDNZM6",
+                   "mAVWg5AIgMk3PAn3UowqTLEQfNlol6GsXPe+kuOWJSCU0cbgbcs8",
+                   "uAVWg5AIgMk3PAn3UowqTLEQfNlol6GsXPe-kuOWJSCU0cbgbcs8"] {
+            let parsed: Cid = s.parse().unwrap();
+            assert_eq!(parsed, cid);
+            assert_eq!(parsed.codec(), RAW);
+            assert_eq!(Blake2bResult::from_cid(&parsed), Ok(hash));
+        }
+
+        let cid = super::blake2s(16, b"hello");
+        assert_eq!(Cid::new(DAG_PB, &cid).to_string(), "bafynbzaccclnkolfhw7yihbyjnj5l4cgldsq");
+    }
+
+    #[test]
+    fn test_cid_invalid() {
+        assert_eq!("".parse::<Cid>().err(), Some(Error::UnsupportedBase));
+        assert_eq!("zb2rhe5P4gXftAwvA4eXQ5HJwsER2owDyS9sKaQRRVQPn93bA".parse::<Cid>().err(),
+                   Some(Error::UnsupportedBase));
+        assert_eq!("f0055a0e402".parse::<Cid>().err(), Some(Error::UnsupportedVersion));
+        assert_eq!("f0155a0e40220".parse::<Cid>().err(), Some(Error::InvalidLength));
+        assert_eq!("bafk2bzace!".parse::<Cid>().err(),
+                   Some(Error::Encoding(::encoding::Error::InvalidCharacter)));
+    }
+}
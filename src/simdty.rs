@@ -70,7 +70,7 @@ pub type u8x32 = Simd32<u8>;
 #[cfg_attr(feature = "cargo-clippy", allow(inline_always))]
 impl<T> Simd4<T> {
     #[inline(always)]
-    pub fn new(e0: T, e1: T, e2: T, e3: T) -> Self {
+    pub const fn new(e0: T, e1: T, e2: T, e3: T) -> Self {
         Simd4(e0, e1, e2, e3)
     }
 }
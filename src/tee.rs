@@ -0,0 +1,345 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+macro_rules! blake2_tee_impl {
+    ($state:ident, $result:ident) => {
+        fn digest_mismatch() -> ::std::io::Error {
+            ::std::io::Error::new(::std::io::ErrorKind::InvalidData,
+                                  "hash mismatch")
+        }
+
+        /// Reader adapter which hashes the data read through it.
+        ///
+        /// Exactly the bytes returned by each read are fed into the
+        /// hashing context. With the `futures-io` or `tokio` features,
+        /// it also works as an asynchronous reader.
+        #[derive(Debug)]
+        pub struct HashingReader<R> {
+            inner: R,
+            state: $state,
+            expected: Option<$result>,
+        }
+
+        impl<R> HashingReader<R> {
+            /// Creates a reader adapter with a hashing context.
+            pub fn new(inner: R, state: $state) -> Self {
+                HashingReader { inner, state, expected: None }
+            }
+
+            /// Creates a reader adapter which verifies the hash at the end.
+            ///
+            /// When the underlying reader reaches its end, if the hash of
+            /// everything read does not match the expected hash (including
+            /// when their lengths differ), the read returns an error of kind
+            /// `InvalidData` instead.
+            pub fn verifying(inner: R, state: $state, expected: &$result) -> Self {
+                HashingReader {
+                    inner,
+                    state,
+                    expected: Some(*expected),
+                }
+            }
+
+            /// Returns a reference to the underlying reader.
+            pub fn get_ref(&self) -> &R { &self.inner }
+
+            /// Consumes the adapter, returning the underlying reader and
+            /// the hash of everything read.
+            pub fn into_inner_and_result(self) -> (R, $result) {
+                (self.inner, self.state.finalize())
+            }
+
+            fn consume(&mut self, data: &[u8], eof: bool)
+                       -> ::std::io::Result<()> {
+                self.state.update(data);
+                if eof {
+                    if let Some(expected) = self.expected {
+                        if self.state.clone().finalize() != expected {
+                            return Err(digest_mismatch());
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        impl<R: ::std::io::Read> ::std::io::Read for HashingReader<R> {
+            fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+                let len = self.inner.read(buf)?;
+                self.consume(&buf[..len], len == 0 && !buf.is_empty())?;
+                Ok(len)
+            }
+        }
+
+        #[cfg(feature = "futures-io")]
+        impl<R> ::futures_io::AsyncRead for HashingReader<R>
+            where R: ::futures_io::AsyncRead + Unpin
+        {
+            fn poll_read(self: ::core::pin::Pin<&mut Self>,
+                         cx: &mut ::core::task::Context,
+                         buf: &mut [u8])
+                         -> ::core::task::Poll<::std::io::Result<usize>> {
+                use core::task::Poll;
+
+                let this = self.get_mut();
+                match ::core::pin::Pin::new(&mut this.inner).poll_read(cx, buf) {
+                    Poll::Ready(Ok(len)) => Poll::Ready(
+                        this.consume(&buf[..len], len == 0 && !buf.is_empty())
+                            .map(|()| len)),
+                    other => other,
+                }
+            }
+        }
+
+        #[cfg(feature = "tokio")]
+        impl<R> ::tokio::io::AsyncRead for HashingReader<R>
+            where R: ::tokio::io::AsyncRead + Unpin
+        {
+            fn poll_read(self: ::core::pin::Pin<&mut Self>,
+                         cx: &mut ::core::task::Context,
+                         buf: &mut ::tokio::io::ReadBuf)
+                         -> ::core::task::Poll<::std::io::Result<()>> {
+                use core::task::Poll;
+
+                let this = self.get_mut();
+                let start = buf.filled().len();
+                let eof = buf.remaining() > 0;
+                match ::core::pin::Pin::new(&mut this.inner).poll_read(cx, buf) {
+                    Poll::Ready(Ok(())) => {
+                        let data = &buf.filled()[start..];
+                        Poll::Ready(this.consume(data, eof && data.is_empty()))
+                    }
+                    other => other,
+                }
+            }
+        }
+
+        /// Writer adapter which hashes the data written through it.
+        ///
+        /// Exactly the bytes accepted by each write of the underlying
+        /// writer are fed into the hashing context. With the `futures-io`
+        /// or `tokio` features, it also works as an asynchronous writer.
+        #[derive(Debug)]
+        pub struct HashingWriter<W> {
+            inner: W,
+            state: $state,
+            expected: Option<$result>,
+        }
+
+        impl<W> HashingWriter<W> {
+            /// Creates a writer adapter with a hashing context.
+            pub fn new(inner: W, state: $state) -> Self {
+                HashingWriter { inner, state, expected: None }
+            }
+
+            /// Creates a writer adapter which verifies the hash at the end.
+            ///
+            /// The hash is verified by `finish` or `into_verified_inner`; a
+            /// hash of a different length never matches.
+            pub fn verifying(inner: W, state: $state, expected: &$result) -> Self {
+                HashingWriter {
+                    inner,
+                    state,
+                    expected: Some(*expected),
+                }
+            }
+
+            /// Returns a reference to the underlying writer.
+            pub fn get_ref(&self) -> &W { &self.inner }
+
+            /// Consumes the adapter, returning the underlying writer and
+            /// the hash of everything written.
+            pub fn into_inner_and_result(self) -> (W, $result) {
+                (self.inner, self.state.finalize())
+            }
+
+            /// Consumes the adapter, returning the underlying writer.
+            ///
+            /// If the adapter was created by `verifying` and the hash of
+            /// everything written does not match the expected hash,
+            /// returns an error of kind `InvalidData` instead. Unlike
+            /// `finish`, the underlying writer is not flushed, so this can
+            /// be used after closing an asynchronous writer.
+            pub fn into_verified_inner(self) -> ::std::io::Result<W> {
+                let (inner, result) = (self.inner, self.state.finalize());
+                match self.expected {
+                    Some(expected) if result != expected
+                        => Err(digest_mismatch()),
+                    _ => Ok(inner),
+                }
+            }
+        }
+
+        impl<W: ::std::io::Write> HashingWriter<W> {
+            /// Flushes and consumes the adapter, returning the underlying
+            /// writer.
+            ///
+            /// If the adapter was created by `verifying` and the hash of
+            /// everything written does not match the expected hash,
+            /// returns an error of kind `InvalidData` instead.
+            pub fn finish(mut self) -> ::std::io::Result<W> {
+                self.inner.flush()?;
+                self.into_verified_inner()
+            }
+        }
+
+        impl<W: ::std::io::Write> ::std::io::Write for HashingWriter<W> {
+            fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+                let len = self.inner.write(buf)?;
+                self.state.update(&buf[..len]);
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> ::std::io::Result<()> {
+                self.inner.flush()
+            }
+        }
+
+        #[cfg(feature = "futures-io")]
+        impl<W> ::futures_io::AsyncWrite for HashingWriter<W>
+            where W: ::futures_io::AsyncWrite + Unpin
+        {
+            fn poll_write(self: ::core::pin::Pin<&mut Self>,
+                          cx: &mut ::core::task::Context,
+                          buf: &[u8])
+                          -> ::core::task::Poll<::std::io::Result<usize>> {
+                use core::task::Poll;
+
+                let this = self.get_mut();
+                let poll = ::core::pin::Pin::new(&mut this.inner)
+                    .poll_write(cx, buf);
+                if let Poll::Ready(Ok(len)) = poll {
+                    this.state.update(&buf[..len]);
+                }
+                poll
+            }
+
+            fn poll_flush(self: ::core::pin::Pin<&mut Self>,
+                          cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::pin::Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+            }
+
+            fn poll_close(self: ::core::pin::Pin<&mut Self>,
+                          cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::pin::Pin::new(&mut self.get_mut().inner).poll_close(cx)
+            }
+        }
+
+        #[cfg(feature = "tokio")]
+        impl<W> ::tokio::io::AsyncWrite for HashingWriter<W>
+            where W: ::tokio::io::AsyncWrite + Unpin
+        {
+            fn poll_write(self: ::core::pin::Pin<&mut Self>,
+                          cx: &mut ::core::task::Context,
+                          buf: &[u8])
+                          -> ::core::task::Poll<::std::io::Result<usize>> {
+                use core::task::Poll;
+
+                let this = self.get_mut();
+                let poll = ::core::pin::Pin::new(&mut this.inner)
+                    .poll_write(cx, buf);
+                if let Poll::Ready(Ok(len)) = poll {
+                    this.state.update(&buf[..len]);
+                }
+                poll
+            }
+
+            fn poll_flush(self: ::core::pin::Pin<&mut Self>,
+                          cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::pin::Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+            }
+
+            fn poll_shutdown(self: ::core::pin::Pin<&mut Self>,
+                             cx: &mut ::core::task::Context)
+                             -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::pin::Pin::new(&mut self.get_mut().inner)
+                    .poll_shutdown(cx)
+            }
+        }
+
+        #[cfg(feature = "futures-io")]
+        impl ::futures_io::AsyncWrite for $state {
+            /// Hashes at most `async_io::BATCH_LEN` bytes per call.
+            fn poll_write(self: ::core::pin::Pin<&mut Self>,
+                          _cx: &mut ::core::task::Context,
+                          buf: &[u8])
+                          -> ::core::task::Poll<::std::io::Result<usize>> {
+                let len = ::core::cmp::min(buf.len(), $crate::async_io::BATCH_LEN);
+                ::core::task::Poll::Ready(
+                    ::std::io::Write::write(self.get_mut(), &buf[..len]))
+            }
+
+            fn poll_flush(self: ::core::pin::Pin<&mut Self>,
+                          _cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::task::Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(self: ::core::pin::Pin<&mut Self>,
+                          _cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::task::Poll::Ready(Ok(()))
+            }
+        }
+
+        #[cfg(feature = "tokio")]
+        impl ::tokio::io::AsyncWrite for $state {
+            /// Hashes at most `async_io::BATCH_LEN` bytes per call.
+            fn poll_write(self: ::core::pin::Pin<&mut Self>,
+                          _cx: &mut ::core::task::Context,
+                          buf: &[u8])
+                          -> ::core::task::Poll<::std::io::Result<usize>> {
+                let len = ::core::cmp::min(buf.len(), $crate::async_io::BATCH_LEN);
+                ::core::task::Poll::Ready(
+                    ::std::io::Write::write(self.get_mut(), &buf[..len]))
+            }
+
+            fn poll_flush(self: ::core::pin::Pin<&mut Self>,
+                          _cx: &mut ::core::task::Context)
+                          -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::task::Poll::Ready(Ok(()))
+            }
+
+            fn poll_shutdown(self: ::core::pin::Pin<&mut Self>,
+                             _cx: &mut ::core::task::Context)
+                             -> ::core::task::Poll<::std::io::Result<()>> {
+                ::core::task::Poll::Ready(Ok(()))
+            }
+        }
+
+        /// Convenience function for hashing everything read from an
+        /// asynchronous reader.
+        ///
+        /// The returned future yields to the executor after hashing each
+        /// `async_io::BATCH_LEN` bytes.
+        #[cfg(feature = "futures-io")]
+        pub fn hash_async_reader<R>(nn: usize, k: &[u8], reader: R)
+            -> $crate::async_io::futures::HashAsyncReader<$state, R, $result>
+            where R: ::futures_io::AsyncRead + Unpin
+        {
+            $crate::async_io::futures::HashAsyncReader::new(
+                $state::with_key(nn, k), reader, $state::finalize)
+        }
+
+        /// Convenience function for hashing everything read from a tokio
+        /// asynchronous reader.
+        ///
+        /// The returned future yields to the executor after hashing each
+        /// `async_io::BATCH_LEN` bytes.
+        #[cfg(feature = "tokio")]
+        pub fn hash_tokio_reader<R>(nn: usize, k: &[u8], reader: R)
+            -> $crate::async_io::tokio::HashAsyncReader<$state, R, $result>
+            where R: ::tokio::io::AsyncRead + Unpin
+        {
+            $crate::async_io::tokio::HashAsyncReader::new(
+                $state::with_key(nn, k), reader, $state::finalize)
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Fiat–Shamir transcripts based on BLAKE2b.
+//!
+//! A `Transcript` absorbs the messages of an interactive protocol, and
+//! produces challenges which depend on all the previous messages and
+//! challenges, in the style of Merlin. The transcript is a BLAKE2b state
+//! personalized with `blake2-rfc trans`, and each operation is hashed
+//! with unambiguous framing:
+//!
+//! ```text
+//! append_message:  0x00 || LE32(len(label)) || label || LE64(len(message)) || message
+//! challenge_bytes: 0x01 || LE32(len(label)) || label || LE64(len(output))
+//! fork:            0x02 || LE32(len(label)) || label || LE64(0)
+//! ```
+//!
+//! A challenge is the BLAKE2Xb output (BLAKE2b for challenges up to 64
+//! bytes) personalized with `blake2-rfc chall`, over the 64-byte hash of
+//! the transcript so far. `challenge_wide` gives 64 bytes, enough to be
+//! reduced into a scalar of up to 256 bits with a negligible bias.
+//!
+//! `build_rng` derives a generator for the prover's randomness from the
+//! transcript, the prover's secrets and fresh entropy, so that a weak
+//! entropy source alone doesn't reveal the secrets.
+//!
+//! Cloning a transcript is cheap; it only copies the BLAKE2b state.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::transcript::Transcript;
+//!
+//! let mut transcript = Transcript::new(b"example protocol");
+//! transcript.append_message(b"commitment", b"...");
+//! let challenge = transcript.challenge_wide(b"challenge");
+//! # let _ = challenge;
+//! ```
+
+use core::fmt;
+
+use blake2b::{Blake2b, Params};
+use drbg::Drbg;
+use kdf::blake2x;
+
+/// Personalization of the transcript state.
+const TRANSCRIPT_PERSONAL: &[u8] = b"blake2-rfc trans";
+
+/// Personalization of the challenges.
+const CHALLENGE_PERSONAL: &[u8] = b"blake2-rfc chall";
+
+/// Personalization string of the prover's generator.
+const RNG_PERSONAL: &[u8] = b"blake2-rfc transcript rng";
+
+const OP_APPEND: u8 = 0x00;
+const OP_CHALLENGE: u8 = 0x01;
+const OP_FORK: u8 = 0x02;
+
+/// Fiat–Shamir transcript.
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    state: Blake2b,
+}
+
+impl Transcript {
+    /// Creates a transcript for a protocol, identified by its label.
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Transcript {
+            state: Params::new(64).personal(TRANSCRIPT_PERSONAL).to_state(None),
+        };
+        transcript.append_message(b"dom-sep", label);
+        transcript
+    }
+
+    /// Appends a labeled message.
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.append_op(OP_APPEND, label, message.len() as u64);
+        self.state.update(message);
+    }
+
+    /// Appends a labeled integer, as 8 little-endian bytes.
+    pub fn append_u64(&mut self, label: &[u8], x: u64) {
+        self.append_message(label, &x.to_le_bytes());
+    }
+
+    /// Fills `dest` with a labeled challenge.
+    ///
+    /// The length of `dest` is part of the transcript, so challenges of
+    /// different lengths are unrelated.
+    pub fn challenge_bytes(&mut self, label: &[u8], dest: &mut [u8]) {
+        self.append_op(OP_CHALLENGE, label, dest.len() as u64);
+        if !dest.is_empty() {
+            let seed = self.state.clone().finalize();
+            blake2x(dest, &[], &[], CHALLENGE_PERSONAL, seed.as_bytes());
+        }
+    }
+
+    /// Returns a 64-byte labeled challenge, to be reduced into a scalar.
+    pub fn challenge_wide(&mut self, label: &[u8]) -> [u8; 64] {
+        let mut out = [0; 64];
+        self.challenge_bytes(label, &mut out);
+        out
+    }
+
+    /// Returns an independent copy of the transcript, separated from
+    /// this one and from other forks by its label.
+    pub fn fork(&self, label: &[u8]) -> Transcript {
+        let mut fork = self.clone();
+        fork.append_op(OP_FORK, label, 0);
+        fork
+    }
+
+    /// Starts building a generator for the prover's randomness.
+    pub fn build_rng(&self) -> TranscriptRngBuilder {
+        TranscriptRngBuilder { transcript: self.fork(b"rng") }
+    }
+
+    fn append_op(&mut self, op: u8, label: &[u8], len: u64) {
+        assert!(label.len() <= u32::MAX as usize);
+        self.state.update(&[op]);
+        self.state.update(&(label.len() as u32).to_le_bytes());
+        self.state.update(label);
+        self.state.update(&len.to_le_bytes());
+    }
+}
+
+/// Builder of a generator for the prover's randomness.
+#[derive(Clone)]
+pub struct TranscriptRngBuilder {
+    transcript: Transcript,
+}
+
+impl TranscriptRngBuilder {
+    /// Adds the prover's secret data.
+    pub fn rekey_with_witness_bytes(mut self, label: &[u8], witness: &[u8])
+                                    -> Self {
+        self.transcript.append_message(label, witness);
+        self
+    }
+
+    /// Builds the generator, with fresh entropy.
+    pub fn finalize(self, entropy: &[u8; 32]) -> Drbg {
+        let seed = self.transcript.state.finalize();
+        Drbg::new(seed.as_bytes(), entropy, RNG_PERSONAL)
+    }
+
+    /// Builds the generator, with fresh entropy from `rng`.
+    #[cfg(feature = "rand_core")]
+    pub fn finalize_with_rng<R>(self, rng: &mut R) -> Drbg
+        where R: ::rand_core::CryptoRng + ?Sized
+    {
+        let mut entropy = [0; 32];
+        rng.fill_bytes(&mut entropy);
+        self.finalize(&entropy)
+    }
+}
+
+impl fmt::Debug for TranscriptRngBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TranscriptRngBuilder").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use super::Transcript;
+
+    #[test]
+    fn test_challenges() {
+        let mut transcript = Transcript::new(b"test protocol");
+        transcript.append_message(b"some label", b"some data");
+        transcript.append_u64(b"n", 42);
+
+        let mut challenge = [0; 32];
+        transcript.challenge_bytes(b"challenge", &mut challenge);
+        assert_eq!(&challenge[..], &HEXUPPER.decode(
+            b"36631E2CE68476AD46C969CE9B48033F9AE4757AED4156FDA10EA4DCF50FAB1D")
+            .unwrap()[..]);
+
+        let wide = transcript.challenge_wide(b"wide");
+        assert_eq!(&wide[..], &HEXUPPER.decode(
+            b"CEEFF7E0063889282F781012E2F779897FF4EFFD468B96BE96DD8744931469C768C9717211B7E0AC442783DD15C213E5C7299EE93594DA4B46F87344C1DB4FFB")
+            .unwrap()[..]);
+
+        let mut long = [0; 100];
+        transcript.challenge_bytes(b"long", &mut long);
+        assert_eq!(&long[..], &HEXUPPER.decode(
+            b"4A3AE3C52C43AC6FF7D63314ACC56A68C4046CBC3FB36139689CF75066706F0B337101A1D3B8352E18126766E05A5D4DA455BEA9481248584ACB4C7DD7A8A74AC909C0932757FC37C5A7C65F9353A0BD10F11FA81582A027A2896439F6A7A2E5396994D2")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut a = Transcript::new(b"test protocol");
+        a.append_message(b"ab", b"c");
+        let mut b = Transcript::new(b"test protocol");
+        b.append_message(b"a", b"bc");
+        assert_ne!(a.challenge_wide(b"x")[..], b.challenge_wide(b"x")[..]);
+
+        // The output length is part of the transcript.
+        let mut a = Transcript::new(b"test protocol");
+        let mut b = a.clone();
+        let mut short = [0; 16];
+        a.challenge_bytes(b"x", &mut short);
+        let mut long = [0; 32];
+        b.challenge_bytes(b"x", &mut long);
+        assert_ne!(short[..], long[..16]);
+    }
+
+    #[test]
+    fn test_fork() {
+        let transcript = Transcript::new(b"test protocol");
+        let mut a = transcript.fork(b"a");
+        let mut a2 = transcript.fork(b"a");
+        let mut b = transcript.fork(b"b");
+        let ca = a.challenge_wide(b"x");
+        assert_eq!(ca[..], a2.challenge_wide(b"x")[..]);
+        assert_ne!(ca[..], b.challenge_wide(b"x")[..]);
+        assert_ne!(ca[..], transcript.clone().challenge_wide(b"x")[..]);
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut transcript = Transcript::new(b"test protocol");
+        transcript.append_message(b"statement", b"public");
+
+        let mut rng = transcript.build_rng()
+            .rekey_with_witness_bytes(b"witness", b"secret")
+            .finalize(&[7; 32]);
+        let mut out = [0; 32];
+        rng.generate(&mut out, &[]).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"44FE42B801CF08B4B7A47C290B2A5DBF007B95DD8E51DB6CEA5BF7867BD9A0B9")
+            .unwrap()[..]);
+
+        // Building the generator doesn't change the transcript.
+        let mut copy = Transcript::new(b"test protocol");
+        copy.append_message(b"statement", b"public");
+        assert_eq!(transcript.challenge_wide(b"x")[..],
+                   copy.challenge_wide(b"x")[..]);
+    }
+}
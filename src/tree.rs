@@ -0,0 +1,219 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! BLAKE2b in tree hashing mode.
+//!
+//! The input is split into leaves of `CHUNK_LEN` bytes (only the last
+//! leaf can be shorter, and an empty input has a single empty leaf).
+//! Each level of the tree hashes the concatenated digests of pairs of
+//! consecutive nodes from the level below it; when a level has an odd
+//! number of nodes, its last node is hashed alone. The root is the only
+//! node of the topmost level.
+//!
+//! Every node is hashed with a fanout of 2, an unlimited maximal depth,
+//! a leaf length of `CHUNK_LEN`, an inner length of 64, its own node
+//! depth (0 for the leaves) and node offset (its index within its
+//! level), and the last node flag set if it is the last node of its
+//! level.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::tree;
+//!
+//! let hash = tree::hash(b"The quick brown fox jumps over the lazy dog");
+//! ```
+
+use blake2b::{Blake2b, Blake2bResult, Params};
+
+/// Length of each leaf of the tree.
+pub const CHUNK_LEN: usize = 4096;
+
+/// Length of each node digest.
+pub const HASH_LEN: usize = 64;
+
+/// Computes the tree hash of the input.
+pub fn hash(data: &[u8]) -> Blake2bResult {
+    let chunks = chunk_count(data.len() as u64);
+    subtree(data, chunks, root_depth(chunks), 0)
+}
+
+/// Computes the tree hash of the input, hashing the subtrees in parallel.
+///
+/// The result is the same as with `hash`.
+#[cfg(feature = "rayon")]
+pub fn par_hash(data: &[u8]) -> Blake2bResult {
+    let chunks = chunk_count(data.len() as u64);
+    par_subtree(data, chunks, root_depth(chunks), 0)
+}
+
+/// Depth below which subtrees are hashed sequentially.
+#[cfg(feature = "rayon")]
+const PAR_MIN_DEPTH: u32 = 4;
+
+#[cfg(feature = "rayon")]
+fn par_subtree(data: &[u8], chunks: u64, depth: u32, offset: u64)
+               -> Blake2bResult {
+    if depth <= PAR_MIN_DEPTH {
+        return subtree(data, chunks, depth, offset);
+    }
+
+    let children = children(chunks, depth, offset);
+    let mut state = parent(depth, offset);
+    if children.end - children.start == 2 {
+        let (left, right) = ::rayon::join(
+            || par_subtree(data, chunks, depth - 1, children.start),
+            || par_subtree(data, chunks, depth - 1, children.start + 1));
+        state.update(left.as_bytes());
+        state.update(right.as_bytes());
+    } else {
+        state.update(par_subtree(data, chunks, depth - 1, children.start)
+                         .as_bytes());
+    }
+    finalize(state, chunks, depth, offset)
+}
+
+fn subtree(data: &[u8], chunks: u64, depth: u32, offset: u64) -> Blake2bResult {
+    if depth == 0 {
+        let start = (offset as usize) * CHUNK_LEN;
+        let end = (start + CHUNK_LEN).min(data.len());
+        let mut state = leaf(offset);
+        state.update(&data[start..end]);
+        return finalize(state, chunks, 0, offset);
+    }
+
+    let mut state = parent(depth, offset);
+    for child in children(chunks, depth, offset) {
+        state.update(subtree(data, chunks, depth - 1, child).as_bytes());
+    }
+    finalize(state, chunks, depth, offset)
+}
+
+/// Returns the number of leaves for an input length.
+pub(crate) fn chunk_count(len: u64) -> u64 {
+    ((len + CHUNK_LEN as u64 - 1) / CHUNK_LEN as u64).max(1)
+}
+
+/// Returns the depth of the root node.
+pub(crate) fn root_depth(chunks: u64) -> u32 {
+    64 - (chunks - 1).leading_zeros()
+}
+
+/// Returns the number of nodes at a given depth.
+pub(crate) fn level_count(chunks: u64, depth: u32) -> u64 {
+    ((chunks - 1) >> depth) + 1
+}
+
+/// Returns the node offsets of the children of a parent node.
+pub(crate) fn children(chunks: u64, depth: u32, offset: u64)
+                       -> ::core::ops::Range<u64> {
+    let count = level_count(chunks, depth - 1);
+    (offset * 2)..(offset * 2 + 2).min(count)
+}
+
+/// Returns the range of input bytes covered by a node.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) fn content_range(len: u64, depth: u32, offset: u64)
+                            -> ::core::ops::Range<u64> {
+    let chunk = CHUNK_LEN as u64;
+    let start = (offset << depth) * chunk;
+    let end = ((offset + 1) << depth).saturating_mul(chunk).min(len);
+    start..end
+}
+
+/// Creates the hashing context for a leaf node.
+pub(crate) fn leaf(offset: u64) -> Blake2b {
+    params(0, offset).to_state(None)
+}
+
+/// Creates the hashing context for a parent node.
+pub(crate) fn parent(depth: u32, offset: u64) -> Blake2b {
+    params(depth, offset).to_state(None)
+}
+
+/// Finalizes a node, setting the last node flag when necessary.
+pub(crate) fn finalize(state: Blake2b, chunks: u64, depth: u32, offset: u64)
+                       -> Blake2bResult {
+    if offset + 1 == level_count(chunks, depth) {
+        state.finalize_last_node()
+    } else {
+        state.finalize()
+    }
+}
+
+fn params(depth: u32, offset: u64) -> Params {
+    Params::new(HASH_LEN)
+        .fanout(2)
+        .max_depth(255)
+        .leaf_length(CHUNK_LEN as u32)
+        .node_offset(offset)
+        .node_depth(depth as u8)
+        .inner_length(HASH_LEN as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use blake2::selftest_seq;
+    use blake2b::blake2b;
+    use super::{CHUNK_LEN, hash, leaf, parent};
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(&hash(b""), &HEXUPPER.decode(
+            b"CD8548D813C5DF811DBE4A45DD2568BCE277289556D8F4CBD7F98E87E0B4F4CEE5F50C7331503E1D9640A1054F1B6DD3F1C574DAA025ABEF237FDAF49062D359")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_not_sequential() {
+        let data = selftest_seq(1024);
+        assert!(hash(&data) != blake2b(64, &[], &data));
+    }
+
+    #[test]
+    fn test_three_chunks() {
+        let mut data = [0; 3 * CHUNK_LEN - 1];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut l0 = leaf(0);
+        l0.update(&data[..CHUNK_LEN]);
+        let mut l1 = leaf(1);
+        l1.update(&data[CHUNK_LEN..2 * CHUNK_LEN]);
+        let mut l2 = leaf(2);
+        l2.update(&data[2 * CHUNK_LEN..]);
+
+        let mut p0 = parent(1, 0);
+        p0.update(l0.finalize().as_bytes());
+        p0.update(l1.finalize().as_bytes());
+        let mut p1 = parent(1, 1);
+        p1.update(l2.finalize_last_node().as_bytes());
+
+        let mut root = parent(2, 0);
+        root.update(p0.finalize().as_bytes());
+        root.update(p1.finalize_last_node().as_bytes());
+
+        assert_eq!(hash(&data), root.finalize_last_node());
+        assert_eq!(&hash(&data), &HEXUPPER.decode(
+            b"55992D739EC4626B81BF0BDADBB2091713E5275F6AE704F64CF66638D4C1DB9CB9897F065C52C6E0DEAADA6D24A8F466619E6572365E1A51138860C79046C948")
+            .unwrap()[..]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_hash() {
+        let data = [0x5A; 100 * CHUNK_LEN + 7];
+        for &len in &[0, CHUNK_LEN, 16 * CHUNK_LEN, 17 * CHUNK_LEN + 1,
+                      32 * CHUNK_LEN, data.len()] {
+            assert_eq!(super::par_hash(&data[..len]), hash(&data[..len]));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use core::cmp;
+
+/// Encodes an integer as in NIST SP 800-185: the big-endian bytes of
+/// the integer without leading zeros (at least one byte), preceded
+/// (`left_encode`) or followed (`right_encode`) by their count.
+pub(crate) fn encode_int(x: u128, buf: &mut [u8; 17], left: bool) -> &[u8] {
+    let bytes = x.to_be_bytes();
+    let skip = cmp::min(x.leading_zeros() as usize / 8, 15);
+    let n = 16 - skip;
+    if left {
+        buf[0] = n as u8;
+        buf[1..n + 1].copy_from_slice(&bytes[skip..]);
+    } else {
+        buf[..n].copy_from_slice(&bytes[skip..]);
+        buf[n] = n as u8;
+    }
+    &buf[..n + 1]
+}
+
+macro_rules! blake2_tuple_impl {
+    ($state:ident, $result:ident) => {
+        /// Hashing context for an unambiguous tuple of byte strings.
+        ///
+        /// Like TupleHash from NIST SP 800-185, each field is preceded by
+        /// its length in bits (with `left_encode`), and the output length
+        /// in bits is appended at the end (with `right_encode`), so no two
+        /// distinct tuples are hashed the same way. The customization
+        /// string is the personalization parameter of the hash; it's padded
+        /// with zeros, so a trailing zero byte doesn't change it.
+        #[derive(Clone, Debug)]
+        pub struct TupleHash {
+            state: $state,
+        }
+
+        impl TupleHash {
+            /// Creates a tuple hashing context with a customization string.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the customization string is longer than the
+            /// personalization parameter, which has 16 bytes for BLAKE2b
+            /// and 8 bytes for BLAKE2s.
+            pub fn new(nn: usize, customization: &[u8]) -> Self {
+                Self::with_key(nn, &[], customization)
+            }
+
+            /// Creates a keyed tuple hashing context with a customization
+            /// string.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the customization string is longer than the
+            /// personalization parameter, which has 16 bytes for BLAKE2b
+            /// and 8 bytes for BLAKE2s.
+            pub fn with_key(nn: usize, k: &[u8], customization: &[u8]) -> Self {
+                TupleHash {
+                    state: Params::new(nn)
+                        .personal(customization)
+                        .to_state(Some(k)),
+                }
+            }
+
+            /// Hashes the next field of the tuple.
+            pub fn push_field(&mut self, field: &[u8]) {
+                let mut buf = [0; 17];
+                let bits = field.len() as u128 * 8;
+                self.state.update($crate::tuple::encode_int(bits, &mut buf, true));
+                self.state.update(field);
+            }
+
+            /// Consumes the context and returns the resulting hash.
+            pub fn finish(mut self) -> $result {
+                let mut buf = [0; 17];
+                let bits = self.state.nn as u128 * 8;
+                self.state.update($crate::tuple::encode_int(bits, &mut buf, false));
+                self.state.finalize()
+            }
+        }
+
+        /// Convenience function for tuple hashing all fields at once.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the customization string is longer than the
+        /// personalization parameter, which has 16 bytes for BLAKE2b
+        /// and 8 bytes for BLAKE2s.
+        pub fn tuple_hash(nn: usize, customization: &[u8], fields: &[&[u8]]) -> $result {
+            let mut context = TupleHash::new(nn, customization);
+            for field in fields {
+                context.push_field(field);
+            }
+            context.finish()
+        }
+    }
+}
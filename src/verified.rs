@@ -0,0 +1,578 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Verified streaming of BLAKE2b tree hashed data.
+//!
+//! The encoding interleaves the input with the digests of the inner
+//! nodes of its tree hash (see the `tree` module), so that a reader
+//! which only knows the root hash can verify each chunk as soon as it
+//! arrives, instead of having to wait for the end of the data.
+//!
+//! The encoding starts with the input length, as a little-endian
+//! 64-bit integer, followed by the nodes of the tree in pre-order: a
+//! parent node is encoded as the digests of its children (one or two),
+//! and a leaf node as its chunk of input.
+//!
+//! A slice of the encoding (see `extract_slice`) has the same format,
+//! but omits the nodes which do not cover the requested range of the
+//! input. Their digests are still present within their parent nodes,
+//! and serve as the proof for the nodes which were kept.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::prelude::*;
+//! use blake2_rfc::verified::{Decoder, encode};
+//!
+//! let (encoded, hash) = encode(b"The quick brown fox jumps over the lazy dog");
+//!
+//! let mut decoder = Decoder::new(&encoded[..], &hash);
+//! let mut output = Vec::new();
+//! decoder.read_to_end(&mut output).unwrap();
+//! assert_eq!(&output[..], b"The quick brown fox jumps over the lazy dog");
+//! ```
+
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::vec::Vec;
+
+use arrayvec::ArrayVec;
+
+use blake2b::Blake2bResult;
+use tree::{self, HASH_LEN};
+use tree::{chunk_count, children, content_range, level_count, root_depth};
+
+/// Length of the header with the input length.
+pub const HEADER_LEN: usize = 8;
+
+/// Returns the length of the encoding for an input length.
+pub fn encoded_len(len: u64) -> u64 {
+    let chunks = chunk_count(len);
+    HEADER_LEN as u64 + subtree_encoded_len(len, root_depth(chunks), 0)
+}
+
+fn subtree_encoded_len(len: u64, depth: u32, offset: u64) -> u64 {
+    let chunks = chunk_count(len);
+    let range = content_range(len, depth, offset);
+
+    let mut nodes = 0;
+    for level in 0..depth + 1 {
+        let first = offset << (depth - level);
+        let end = cmp::min((offset + 1) << (depth - level),
+                           level_count(chunks, level));
+        nodes += end - first;
+    }
+    (range.end - range.start) + (nodes - 1) * HASH_LEN as u64
+}
+
+/// Encodes the input, returning the encoding and its root hash.
+pub fn encode(input: &[u8]) -> (Vec<u8>, Blake2bResult) {
+    let len = input.len() as u64;
+    let chunks = chunk_count(len);
+
+    let mut out = Vec::with_capacity(encoded_len(len) as usize);
+    out.extend_from_slice(&len.to_le_bytes());
+    let hash = encode_subtree(input, chunks, root_depth(chunks), 0, &mut out);
+    (out, hash)
+}
+
+fn encode_subtree(input: &[u8], chunks: u64, depth: u32, offset: u64,
+                  out: &mut Vec<u8>) -> Blake2bResult {
+    if depth == 0 {
+        let range = content_range(input.len() as u64, 0, offset);
+        let chunk = &input[range.start as usize..range.end as usize];
+        out.extend_from_slice(chunk);
+
+        let mut state = tree::leaf(offset);
+        state.update(chunk);
+        return tree::finalize(state, chunks, 0, offset);
+    }
+
+    let children = children(chunks, depth, offset);
+    let pos = out.len();
+    out.resize(pos + (children.end - children.start) as usize * HASH_LEN, 0);
+
+    let mut state = tree::parent(depth, offset);
+    for (i, child) in children.enumerate() {
+        let hash = encode_subtree(input, chunks, depth - 1, child, out);
+        out[pos + i * HASH_LEN..pos + (i + 1) * HASH_LEN]
+            .copy_from_slice(hash.as_bytes());
+        state.update(hash.as_bytes());
+    }
+    tree::finalize(state, chunks, depth, offset)
+}
+
+/// Extracts a slice of an encoding.
+///
+/// The slice covers the `len` bytes of input starting at `start`, and
+/// can be verified with a `SliceDecoder` for the same range. The
+/// encoding is read from the current position of `encoded`, and is not
+/// verified.
+pub fn extract_slice<R, W>(encoded: &mut R, start: u64, len: u64,
+                           out: &mut W) -> io::Result<()>
+    where R: Read + Seek, W: Write
+{
+    let base = encoded.stream_position()?;
+
+    let mut header = [0; HEADER_LEN];
+    encoded.read_exact(&mut header)?;
+    out.write_all(&header)?;
+
+    let content_len = u64::from_le_bytes(header);
+    let chunks = chunk_count(content_len);
+    let range = Range::new(content_len, start, len);
+
+    let mut stack = vec![(root_depth(chunks), 0, HEADER_LEN as u64)];
+    while let Some((depth, offset, pos)) = stack.pop() {
+        encoded.seek(SeekFrom::Start(base + pos))?;
+
+        if depth == 0 {
+            let chunk = content_range(content_len, 0, offset);
+            let chunk_len = chunk.end - chunk.start;
+            if io::copy(&mut encoded.take(chunk_len), out)? != chunk_len {
+                return Err(eof());
+            }
+            continue;
+        }
+
+        let children = children(chunks, depth, offset);
+        let mut hashes = [0; 2 * HASH_LEN];
+        let hashes = &mut hashes[..(children.end - children.start) as usize
+                                   * HASH_LEN];
+        encoded.read_exact(hashes)?;
+        out.write_all(hashes)?;
+
+        let mut pos = pos + hashes.len() as u64;
+        let mut wanted = Vec::with_capacity(2);
+        for child in children {
+            if range.wanted(content_len, depth - 1, child) {
+                wanted.push((depth - 1, child, pos));
+            }
+            pos += subtree_encoded_len(content_len, depth - 1, child);
+        }
+        stack.extend(wanted.into_iter().rev());
+    }
+    Ok(())
+}
+
+fn eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated encoding")
+}
+
+fn mismatch() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "hash mismatch")
+}
+
+/// Range of the input which is being decoded.
+#[derive(Clone, Copy, Debug)]
+struct Range {
+    start: u64,
+    end: u64,
+}
+
+impl Range {
+    fn new(content_len: u64, start: u64, len: u64) -> Self {
+        // Always include at least one chunk, so that the input length
+        // is verified even for an empty range.
+        let last = content_range(content_len, 0,
+                                 chunk_count(content_len) - 1).start;
+        let start = cmp::min(start, last);
+        let end = cmp::max(start.saturating_add(len), start + 1);
+        Range { start, end }
+    }
+
+    fn wanted(&self, content_len: u64, depth: u32, offset: u64) -> bool {
+        let node = content_range(content_len, depth, offset);
+        node.start < self.end && self.start < cmp::max(node.end, node.start + 1)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    hash: [u8; HASH_LEN],
+    depth: u32,
+    offset: u64,
+}
+
+/// Verifying traversal of an encoding, shared by both decoders.
+struct Verifier {
+    root: [u8; HASH_LEN],
+    start: u64,
+    len: u64,
+    content_len: Option<u64>,
+    range: Range,
+    end: u64,
+    stack: Vec<Node>,
+    buf: Vec<u8>,
+    buf_pos: usize,
+    buf_end: usize,
+    pos: u64,
+    consumed: u64,
+}
+
+impl Verifier {
+    fn new(root: &Blake2bResult, start: u64, len: u64) -> Self {
+        assert_eq!(root.len(), HASH_LEN);
+
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(root.as_bytes());
+        Verifier {
+            root: hash,
+            start,
+            len,
+            content_len: None,
+            range: Range { start: 0, end: 0 },
+            end: 0,
+            stack: Vec::new(),
+            buf: Vec::new(),
+            buf_pos: 0,
+            buf_end: 0,
+            pos: start,
+            consumed: 0,
+        }
+    }
+
+    fn root_node(&self, content_len: u64) -> Node {
+        Node {
+            hash: self.root,
+            depth: root_depth(chunk_count(content_len)),
+            offset: 0,
+        }
+    }
+
+    fn read_header<R: Read>(&mut self, r: &mut R) -> io::Result<u64> {
+        if let Some(content_len) = self.content_len {
+            return Ok(content_len);
+        }
+
+        let mut header = [0; HEADER_LEN];
+        r.read_exact(&mut header)?;
+        self.consumed += HEADER_LEN as u64;
+
+        let content_len = u64::from_le_bytes(header);
+        self.content_len = Some(content_len);
+        self.range = Range::new(content_len, self.start, self.len);
+        self.end = cmp::min(self.start.saturating_add(self.len), content_len);
+        self.stack.push(self.root_node(content_len));
+        Ok(content_len)
+    }
+
+    fn read<R: Read>(&mut self, r: &mut R, buf: &mut [u8])
+                     -> io::Result<usize> {
+        loop {
+            if self.buf_pos < self.buf_end {
+                let len = cmp::min(buf.len(), self.buf_end - self.buf_pos);
+                buf[..len].copy_from_slice(&self.buf[self.buf_pos..][..len]);
+                self.buf_pos += len;
+                self.pos += len as u64;
+                return Ok(len);
+            }
+
+            let content_len = self.read_header(r)?;
+            let node = match self.stack.pop() {
+                Some(node) => node,
+                None => return Ok(0),
+            };
+
+            if node.depth == 0 {
+                self.read_leaf(r, content_len, &node)?;
+                continue;
+            }
+
+            let hashes = self.read_parent(r, content_len, &node)?;
+            let first = children(chunk_count(content_len),
+                                 node.depth, node.offset).start;
+            for (i, hash) in hashes.iter().enumerate().rev() {
+                let child = first + i as u64;
+                if self.range.wanted(content_len, node.depth - 1, child) {
+                    self.stack.push(Node {
+                        hash: *hash,
+                        depth: node.depth - 1,
+                        offset: child,
+                    });
+                }
+            }
+        }
+    }
+
+    fn seek<R: Read + Seek>(&mut self, r: &mut R, pos: SeekFrom)
+                            -> io::Result<u64> {
+        let content_len = self.read_header(r)?;
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => add_signed(content_len, n),
+            SeekFrom::Current(n) => add_signed(self.pos, n),
+        };
+        let target = target.ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput, "invalid seek to a negative position"))?;
+
+        let chunks = chunk_count(content_len);
+        let last = content_range(content_len, 0, chunks - 1).start;
+        let dest = cmp::min(target, last);
+        let base = r.stream_position()? - self.consumed;
+
+        self.stack.clear();
+        self.buf_pos = 0;
+        self.buf_end = 0;
+        self.pos = target;
+
+        let mut node = self.root_node(content_len);
+        let mut encoded = HEADER_LEN as u64;
+        while node.depth > 0 {
+            r.seek(SeekFrom::Start(base + encoded))?;
+            self.consumed = encoded;
+
+            let hashes = self.read_parent(r, content_len, &node)?;
+            encoded += (hashes.len() * HASH_LEN) as u64;
+
+            let depth = node.depth - 1;
+            let left = children(chunks, node.depth, node.offset).start;
+            if hashes.len() == 1 || dest < content_range(content_len, depth, left).end {
+                if hashes.len() == 2 {
+                    self.stack.push(Node { hash: hashes[1], depth, offset: left + 1 });
+                }
+                node = Node { hash: hashes[0], depth, offset: left };
+            } else {
+                encoded += subtree_encoded_len(content_len, depth, left);
+                node = Node { hash: hashes[1], depth, offset: left + 1 };
+            }
+        }
+
+        r.seek(SeekFrom::Start(base + encoded))?;
+        self.consumed = encoded;
+        self.read_leaf(r, content_len, &node)?;
+        Ok(target)
+    }
+
+    /// Reads and verifies a parent node, returning its children hashes.
+    fn read_parent<R: Read>(&mut self, r: &mut R, content_len: u64, node: &Node)
+                            -> io::Result<ArrayVec<[[u8; HASH_LEN]; 2]>> {
+        let chunks = chunk_count(content_len);
+        let children = children(chunks, node.depth, node.offset);
+
+        let mut state = tree::parent(node.depth, node.offset);
+        let mut hashes = ArrayVec::new();
+        for _ in children {
+            let mut hash = [0; HASH_LEN];
+            r.read_exact(&mut hash)?;
+            self.consumed += HASH_LEN as u64;
+            state.update(&hash);
+            hashes.push(hash);
+        }
+
+        if tree::finalize(state, chunks, node.depth, node.offset) != node.hash[..] {
+            return Err(mismatch());
+        }
+        Ok(hashes)
+    }
+
+    /// Reads and verifies a leaf node into the buffer.
+    fn read_leaf<R: Read>(&mut self, r: &mut R, content_len: u64, node: &Node)
+                          -> io::Result<()> {
+        let chunk = content_range(content_len, 0, node.offset);
+        self.buf_pos = 0;
+        self.buf_end = 0;
+        self.buf.resize((chunk.end - chunk.start) as usize, 0);
+        r.read_exact(&mut self.buf)?;
+        self.consumed += chunk.end - chunk.start;
+
+        let mut state = tree::leaf(node.offset);
+        state.update(&self.buf);
+        if tree::finalize(state, chunk_count(content_len), 0, node.offset)
+            != node.hash[..] {
+            return Err(mismatch());
+        }
+
+        let start = cmp::max(self.pos, chunk.start);
+        let end = cmp::min(self.end, chunk.end);
+        if start < end {
+            self.buf_pos = (start - chunk.start) as usize;
+            self.buf_end = (end - chunk.start) as usize;
+        }
+        Ok(())
+    }
+}
+
+fn add_signed(a: u64, b: i64) -> Option<u64> {
+    if b >= 0 {
+        a.checked_add(b as u64)
+    } else {
+        a.checked_sub(b.wrapping_neg() as u64)
+    }
+}
+
+/// Decoder for a full encoding.
+///
+/// The decoded input is verified chunk by chunk against the root hash.
+/// A read which fails verification returns an error of kind
+/// `InvalidData`; no unverified data is ever returned.
+pub struct Decoder<R> {
+    inner: R,
+    verifier: Verifier,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Creates a decoder for an encoding with the given root hash.
+    pub fn new(inner: R, hash: &Blake2bResult) -> Self {
+        Decoder {
+            inner,
+            verifier: Verifier::new(hash, 0, u64::MAX),
+        }
+    }
+
+    /// Reads and returns the length of the input.
+    ///
+    /// The length is only verified when the last chunk is read.
+    pub fn content_len(&mut self) -> io::Result<u64> {
+        self.verifier.read_header(&mut self.inner)
+    }
+
+    /// Consumes the decoder and returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.verifier.read(&mut self.inner, buf)
+    }
+}
+
+impl<R: Read + Seek> Seek for Decoder<R> {
+    /// Seeks to an offset of the decoded input.
+    ///
+    /// The chunk containing the new position (or the last chunk, when
+    /// seeking to or past the end) is read and verified immediately,
+    /// together with the parent nodes above it.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.verifier.seek(&mut self.inner, pos)
+    }
+}
+
+/// Decoder for a slice of an encoding.
+///
+/// The slice must have been extracted with `extract_slice` for the same
+/// range. Only the bytes of the input within that range are returned.
+pub struct SliceDecoder<R> {
+    inner: R,
+    verifier: Verifier,
+}
+
+impl<R: Read> SliceDecoder<R> {
+    /// Creates a decoder for a slice of an encoding.
+    pub fn new(inner: R, hash: &Blake2bResult, start: u64, len: u64) -> Self {
+        SliceDecoder {
+            inner,
+            verifier: Verifier::new(hash, start, len),
+        }
+    }
+
+    /// Consumes the decoder and returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for SliceDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.verifier.read(&mut self.inner, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+    use std::io::{Cursor, ErrorKind, SeekFrom};
+    use std::vec::Vec;
+
+    use tree::{self, CHUNK_LEN};
+    use super::{Decoder, SliceDecoder, encode, encoded_len, extract_slice};
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    const LENGTHS: [usize; 9] = [
+        0, 1, CHUNK_LEN - 1, CHUNK_LEN, CHUNK_LEN + 1, 3 * CHUNK_LEN,
+        4 * CHUNK_LEN, 5 * CHUNK_LEN + 17, 11 * CHUNK_LEN - 1,
+    ];
+
+    #[test]
+    fn test_roundtrip() {
+        for &len in &LENGTHS {
+            let data = input(len);
+            let (encoded, hash) = encode(&data);
+            assert_eq!(hash, tree::hash(&data));
+            assert_eq!(encoded.len() as u64, encoded_len(len as u64));
+
+            let mut output = Vec::new();
+            Decoder::new(&encoded[..], &hash).read_to_end(&mut output).unwrap();
+            assert_eq!(output, data);
+        }
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let data = input(5 * CHUNK_LEN + 17);
+        let (encoded, hash) = encode(&data);
+
+        for &pos in &[0, 8, 100, 8 + 3 * 64, encoded.len() - 1] {
+            let mut corrupted = encoded.clone();
+            corrupted[pos] ^= 1;
+
+            let mut output = Vec::new();
+            let err = Decoder::new(&corrupted[..], &hash)
+                .read_to_end(&mut output).unwrap_err();
+            assert!(err.kind() == ErrorKind::InvalidData ||
+                    err.kind() == ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        for &len in &LENGTHS {
+            let data = input(len);
+            let (encoded, hash) = encode(&data);
+            let mut decoder = Decoder::new(Cursor::new(&encoded), &hash);
+
+            for &pos in &[len / 2, 0, len.saturating_sub(1), len, len + 5,
+                          CHUNK_LEN + 3] {
+                assert_eq!(decoder.seek(SeekFrom::Start(pos as u64)).unwrap(),
+                           pos as u64);
+                let mut output = Vec::new();
+                decoder.read_to_end(&mut output).unwrap();
+                assert_eq!(&output[..], &data[pos.min(len)..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice() {
+        for &len in &LENGTHS {
+            let data = input(len);
+            let (encoded, hash) = encode(&data);
+
+            for &(start, slice_len) in &[(0, len), (CHUNK_LEN - 1, 2),
+                                         (len / 3, len / 3), (len, 10),
+                                         (2 * CHUNK_LEN, 0)] {
+                let mut slice = Vec::new();
+                extract_slice(&mut Cursor::new(&encoded), start as u64,
+                              slice_len as u64, &mut slice).unwrap();
+                assert!(slice.len() <= encoded.len());
+
+                let mut output = Vec::new();
+                SliceDecoder::new(&slice[..], &hash, start as u64,
+                                  slice_len as u64)
+                    .read_to_end(&mut output).unwrap();
+                let start = start.min(len);
+                let end = (start + slice_len).min(len);
+                assert_eq!(&output[..], &data[start..end]);
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Hashing to finite fields with BLAKE2b, as in RFC 9380.
+//!
+//! `expand_message_xmd` is the function of section 5.3.1 of RFC 9380, with
+//! BLAKE2b-512 as the hash function (`b_in_bytes` is 64 and `s_in_bytes`
+//! is 128). Domain separation tags longer than 255 bytes are first hashed
+//! as described in section 5.3.3.
+//!
+//! `hash_to_field` is the function of section 5.2 for prime fields: it
+//! expands the message into `L` bytes per element, and reduces each
+//! `L`-byte big-endian integer modulo the prime, so the result is within
+//! `2^-k` of uniform for a security level of `k` bits. For extension
+//! fields of degree `m`, request `m` times as many elements, and group
+//! them `m` at a time.
+//!
+//! # Examples
+//!
+//! ```
+//! use blake2_rfc::xmd;
+//!
+//! // 2^255 - 19
+//! let mut p = [0xFF; 32];
+//! p[0] = 0x7F;
+//! p[31] = 0xED;
+//!
+//! let mut u = [0; 64];
+//! xmd::hash_to_field(b"message", b"EXAMPLE-V01-CS01", &p, 128, &mut u).unwrap();
+//! ```
+
+use core::fmt;
+
+use blake2b::Blake2b;
+
+/// Output length of the hash function (`b_in_bytes`).
+const HASH_LEN: usize = 64;
+
+/// Block length of the hash function (`s_in_bytes`).
+const BLOCK_LEN: usize = 128;
+
+/// Prefix used to hash domain separation tags longer than `DST_MAX_LEN`.
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+/// Maximum length of a domain separation tag used as is.
+pub const DST_MAX_LEN: usize = 255;
+
+/// Maximum output length of `expand_message_xmd`.
+pub const MAX_LEN: usize = 255 * HASH_LEN;
+
+/// Error returned by the functions of this module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The requested output is longer than `MAX_LEN` bytes.
+    OutputTooLong,
+    /// The modulus is not valid, or the output is not a whole number of
+    /// elements.
+    InvalidModulus,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::OutputTooLong => "expand_message_xmd output too long",
+            Error::InvalidModulus => "invalid hash_to_field modulus",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+/// Fills `out` with `expand_message_xmd(msg, dst, out.len())`.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], out: &mut [u8])
+                          -> Result<(), Error> {
+    let mut expander = Expander::new(msg, dst, out.len())?;
+    expander.fill(out);
+    Ok(())
+}
+
+/// Hashes a message to elements of the prime field defined by `modulus`.
+///
+/// The modulus is a big-endian integer without leading zero bytes, and
+/// `security_bits` is the target security level `k`. Each element is
+/// written to `out` as a big-endian integer of the same length as the
+/// modulus, so `out` must have room for a whole number of elements.
+pub fn hash_to_field(msg: &[u8], dst: &[u8], modulus: &[u8],
+                     security_bits: usize, out: &mut [u8]) -> Result<(), Error> {
+    let n = modulus.len();
+    if n == 0 || modulus[0] == 0 || modulus == [1] || out.len() % n != 0 {
+        return Err(Error::InvalidModulus);
+    }
+
+    let bits = 8 * n - modulus[0].leading_zeros() as usize;
+    let elem_len = (bits + security_bits).div_ceil(8);
+    let len = (out.len() / n).checked_mul(elem_len)
+        .ok_or(Error::OutputTooLong)?;
+    let mut expander = Expander::new(msg, dst, len)?;
+
+    for elem in out.chunks_mut(n) {
+        for x in elem.iter_mut() {
+            *x = 0;
+        }
+        for _ in 0..elem_len {
+            let mut byte = [0];
+            expander.fill(&mut byte);
+            for bit in (0..8).rev() {
+                shift_in(elem, (byte[0] >> bit) & 1, modulus);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Computes `r = (2 * r + bit) mod p`, for `r < p`, without branching on
+/// the values.
+fn shift_in(r: &mut [u8], bit: u8, p: &[u8]) {
+    let mut carry = bit;
+    for x in r.iter_mut().rev() {
+        let top = *x >> 7;
+        *x = (*x << 1) | carry;
+        carry = top;
+    }
+
+    let mut borrow = 0;
+    for (&x, &y) in r.iter().zip(p).rev() {
+        borrow = ((x as u16).wrapping_sub(y as u16 + borrow) >> 15) & 1;
+    }
+
+    // Subtract p if the shift overflowed or if r >= p.
+    let mask = 0u8.wrapping_sub(carry | (borrow as u8 ^ 1));
+    let mut borrow = 0;
+    for (x, &y) in r.iter_mut().zip(p).rev() {
+        let t = (*x as u16).wrapping_sub((y & mask) as u16 + borrow);
+        *x = t as u8;
+        borrow = (t >> 15) & 1;
+    }
+}
+
+/// Computes `H(parts[0] || parts[1] || ...)`.
+fn hash(parts: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut state = Blake2b::new(HASH_LEN);
+    for part in parts {
+        state.update(part);
+    }
+    let mut out = [0; HASH_LEN];
+    out.copy_from_slice(state.finalize().as_bytes());
+    out
+}
+
+/// Incremental output of `expand_message_xmd`.
+struct Expander {
+    dst_prime: [u8; DST_MAX_LEN + 1],
+    dst_prime_len: usize,
+    b_0: [u8; HASH_LEN],
+    b_i: [u8; HASH_LEN],
+    i: u8,
+    pos: usize,
+}
+
+impl Expander {
+    fn new(msg: &[u8], dst: &[u8], len: usize) -> Result<Self, Error> {
+        if len > MAX_LEN {
+            return Err(Error::OutputTooLong);
+        }
+
+        let oversize_dst;
+        let dst = if dst.len() > DST_MAX_LEN {
+            oversize_dst = hash(&[OVERSIZE_DST_PREFIX, dst]);
+            &oversize_dst[..]
+        } else {
+            dst
+        };
+        let mut dst_prime = [0; DST_MAX_LEN + 1];
+        dst_prime[..dst.len()].copy_from_slice(dst);
+        dst_prime[dst.len()] = dst.len() as u8;
+        let dst_prime_len = dst.len() + 1;
+
+        let b_0 = hash(&[&[0; BLOCK_LEN], msg, &(len as u16).to_be_bytes(),
+                         &[0], &dst_prime[..dst_prime_len]]);
+        let b_1 = hash(&[&b_0, &[1], &dst_prime[..dst_prime_len]]);
+        Ok(Expander {
+            dst_prime,
+            dst_prime_len,
+            b_0,
+            b_i: b_1,
+            i: 1,
+            pos: 0,
+        })
+    }
+
+    fn fill(&mut self, out: &mut [u8]) {
+        for x in out.iter_mut() {
+            if self.pos == HASH_LEN {
+                let mut xored = self.b_0;
+                for (x, &y) in xored.iter_mut().zip(&self.b_i) {
+                    *x ^= y;
+                }
+                self.i += 1;
+                self.b_i = hash(&[&xored, &[self.i],
+                                  &self.dst_prime[..self.dst_prime_len]]);
+                self.pos = 0;
+            }
+            *x = self.b_i[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate data_encoding;
+    use self::data_encoding::HEXUPPER;
+
+    use super::{Error, MAX_LEN, expand_message_xmd, hash, hash_to_field};
+
+    const DST: &[u8] = b"QUUX-V01-CS02-with-expander-BLAKE2b-256";
+
+    #[test]
+    fn test_expand_message_xmd() {
+        let mut out = [0; 32];
+        expand_message_xmd(b"", DST, &mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"3CB3AA75851866B2098C5C8B120D3AC774B5510237DC80C8F4790E268720BEEF")
+            .unwrap()[..]);
+
+        expand_message_xmd(b"abc", DST, &mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"7EE1539DFD4591C82203B07E65D0D7215233E34C118E90967CC188F96FC4A793")
+            .unwrap()[..]);
+
+        let mut msg = [b'a'; 517];
+        msg[..5].copy_from_slice(b"a512_");
+        let mut out = [0; 0x80];
+        expand_message_xmd(&msg, DST, &mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"5C0289B93583AD609C3F18657457ECCE03882879860FE301927086FDEE4D605D13C05F4E60AAE2984D36C80F27897CEB004EE3CB5D13766F26070C5267953CB8E69F63AEAEBC8B613E718CB3AA0408FB3CB80A9DEABC79B603504781BFAF0BE188B9CCD2AC58376CD855A2DC46011081A8E96FD2A2DA0010E961C83C33557D69")
+            .unwrap()[..]);
+
+        let mut out = [0; 200];
+        expand_message_xmd(b"abc", DST, &mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"791927884A2C6D0518869474D5ACAA785BE659F51F6968E86ED432B9661070745EAE1E3BC07B02E5CBC005011A75F74E62A49E4DB94EFB1D6AD6995A2361B59073C6715987D6CFFE646D90167EE32B2D752D5D313BBF2DCD7694130C64FE7BFABE435F3BC56E7B1A7F9EC6B6B2D433CD4B1353F294C13A9AC225E8EF60B4884E02F0669E38078BF2BBD2594F3726076291A55F5F22D35072DFF5074134813FC37859CB0F8B3085895B0A20D0571FAF2D7AA89AF5786CFD16BF40E8326A07B432B4A0DC04335EA455")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_oversize_dst() {
+        let dst = [b'X'; 300];
+        let mut out = [0; 48];
+        expand_message_xmd(b"abc", &dst, &mut out).unwrap();
+        assert_eq!(&out[..], &HEXUPPER.decode(
+            b"10A2521B7833AEE180ABE08B71115972D136602B4735BBC6ED8744B7EB20D424BEA25B862B3387945B717B6080A2E66C")
+            .unwrap()[..]);
+
+        let mut out2 = [0; 48];
+        expand_message_xmd(b"abc", &hash(&[b"H2C-OVERSIZE-DST-", &dst]),
+                           &mut out2).unwrap();
+        assert_eq!(&out[..], &out2[..]);
+    }
+
+    #[test]
+    fn test_hash_to_field() {
+        // BLS12-381 base field.
+        let p = HEXUPPER.decode(
+            b"1A0111EA397FE69A4B1BA7B6434BACD764774B84F38512BF6730D2A0F6B0F6241EABFFFEB153FFFFB9FEFFFFFFFFAAAB")
+            .unwrap();
+        let mut u = [0; 96];
+        hash_to_field(b"abc", DST, &p, 128, &mut u).unwrap();
+        assert_eq!(&u[..], &HEXUPPER.decode(
+            b"055991CDF17A805791075AD3592E7AEB65529EE9A305EEF7BCFF873BF8D6739717B371EE8422B4784A93D03465D588CF0D044C169AEFD18CDD7258EE11D0352EC628A1A0E408B6CF1C780EF6818961A9923445FDAD86E0E36D9429CAD0CECA51")
+            .unwrap()[..]);
+
+        // 2^255 - 19.
+        let mut p = [0xFF; 32];
+        p[0] = 0x7F;
+        p[31] = 0xED;
+        let mut u = [0; 32];
+        hash_to_field(b"", DST, &p, 128, &mut u).unwrap();
+        assert_eq!(&u[..], &HEXUPPER.decode(
+            b"4863A7230C672E4548BCB42A8B2BF78E5E554C624CC34B53CCB77BF50EAC4973")
+            .unwrap()[..]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut out = [0; MAX_LEN + 1];
+        assert_eq!(expand_message_xmd(b"", DST, &mut out[..MAX_LEN + 1]),
+                   Err(Error::OutputTooLong));
+        expand_message_xmd(b"", DST, &mut out[..MAX_LEN]).unwrap();
+
+        assert_eq!(hash_to_field(b"", DST, &[], 128, &mut []),
+                   Err(Error::InvalidModulus));
+        assert_eq!(hash_to_field(b"", DST, &[0, 7], 128, &mut [0; 2]),
+                   Err(Error::InvalidModulus));
+        assert_eq!(hash_to_field(b"", DST, &[1], 128, &mut [0; 1]),
+                   Err(Error::InvalidModulus));
+        assert_eq!(hash_to_field(b"", DST, &[1, 7], 128, &mut [0; 3]),
+                   Err(Error::InvalidModulus));
+        assert_eq!(hash_to_field(b"", DST, &[0xFF; 32], 128, &mut [0; 32 * 400]),
+                   Err(Error::OutputTooLong));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * Copyright 2015 blake2-rfc Developers
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+/* Test program for the C API, linked by tests/capi.rs. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "blake2.h"
+
+static int failures = 0;
+
+static void check(const char *name, int ok) {
+  if (!ok) {
+    fprintf(stderr, "FAIL: %s\n", name);
+    failures++;
+  }
+}
+
+static void check_hex(const char *name, const uint8_t *out, size_t len,
+                      const char *hex) {
+  char buf[2 * BLAKE2B_OUTBYTES + 1];
+  size_t i;
+  for (i = 0; i < len; i++)
+    sprintf(buf + 2 * i, "%02x", out[i]);
+  check(name, strlen(hex) == 2 * len && memcmp(buf, hex, 2 * len) == 0);
+}
+
+int main(void) {
+  uint8_t in[256], key[64], out[64];
+  blake2b_state bs;
+  blake2s_state ss;
+  blake2b_param bp;
+  size_t i;
+
+  for (i = 0; i < sizeof in; i++)
+    in[i] = (uint8_t)i;
+  for (i = 0; i < sizeof key; i++)
+    key[i] = (uint8_t)i;
+
+  check("param sizes", sizeof(blake2b_param) == 64 && sizeof(blake2s_param) == 32);
+
+  check("blake2b", blake2b(out, "abc", NULL, 64, 3, 0) == 0);
+  check_hex("blake2b abc", out, 64,
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1"
+            "7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923");
+  check("blake2s", blake2s(out, "abc", NULL, 32, 3, 0) == 0);
+  check_hex("blake2s abc", out, 32,
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982");
+
+  check("blake2b_init_key", blake2b_init_key(&bs, 64, key, 64) == 0);
+  for (i = 0; i < sizeof in; i += 7)
+    check("blake2b_update",
+          blake2b_update(&bs, in + i, i + 7 > sizeof in ? sizeof in - i : 7) == 0);
+  check("blake2b_final", blake2b_final(&bs, out, 64) == 0);
+  check_hex("blake2b keyed", out, 64,
+            "b72071e096277edebb8ee5134dd3714996307ba3a55aa4733d412abbe28e909e"
+            "10e57e6fbfb4ef53b3b960518294ff889a90829254412e2a60b85add07a3674f");
+  check("blake2b_final twice", blake2b_final(&bs, out, 64) == -1);
+
+  check("blake2s_init_key", blake2s_init_key(&ss, 32, key, 32) == 0);
+  check("blake2s_update", blake2s_update(&ss, in, sizeof in) == 0);
+  check("blake2s_final short", blake2s_final(&ss, out, 31) == -1);
+  check("blake2s_final", blake2s_final(&ss, out, 32) == 0);
+  check_hex("blake2s keyed", out, 32,
+            "5211d1aefc0025be7f85c06b3e14e0fc645ae12bd41746485ea6d8a364a2eaee");
+
+  memset(&bp, 0, sizeof bp);
+  bp.digest_length = 32;
+  bp.fanout = 1;
+  bp.depth = 1;
+  memcpy(bp.salt, "0123456789abcdef", 16);
+  memcpy(bp.personal, "personalization!", 16);
+  check("blake2b_init_param", blake2b_init_param(&bs, &bp) == 0);
+  check("blake2b_update", blake2b_update(&bs, (const uint8_t *)"abc", 3) == 0);
+  check("blake2b_final", blake2b_final(&bs, out, 64) == 0);
+  check_hex("blake2b param", out, 32,
+            "dbe8233deda041edda3554928d2d7ce65159a46dd653254596459eb42ce16a24");
+
+  check("blake2bp", blake2bp(out, NULL, NULL, 64, 0, 0) == 0);
+  check_hex("blake2bp empty", out, 64,
+            "b5ef811a8038f70b628fa8b294daae7492b1ebe343a80eaabbf1f6ae664dd67b"
+            "9d90b0120791eab81dc96985f28849f6a305186a85501b405114bfa678df9380");
+  check("blake2sp", blake2sp(out, NULL, NULL, 32, 0, 0) == 0);
+  check_hex("blake2sp empty", out, 32,
+            "dd0e891776933f43c7d032b08a917e25741f8aa9a12c12e1cac8801500f2ca4f");
+
+  check("blake2b outlen 0", blake2b_init(&bs, 0) == -1);
+  check("blake2s outlen 33", blake2s_init(&ss, 33) == -1);
+  check("blake2b key 65", blake2b(out, in, key, 64, 3, 65) == -1);
+  check("blake2b null input", blake2b(out, NULL, NULL, 64, 3, 0) == -1);
+
+  return failures != 0;
+}
@@ -0,0 +1,137 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Builds the C API as a static library, and links a C program against it.
+//! Also checks the `include/blake2.h` header against the Rust declarations.
+//!
+//! These tests need a C compiler (`cc`, or the one named by `CC`).
+
+#![cfg(feature = "capi")]
+
+extern crate blake2_rfc;
+
+use std::env;
+use std::ffi::{c_int, c_void};
+use std::mem;
+use std::path::Path;
+use std::process::Command;
+
+use blake2_rfc::capi::*;
+
+fn cc() -> Command {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+    let mut command = Command::new(&cc);
+    if Command::new(&cc).arg("--version").output().is_err() {
+        panic!("the C API tests need a C compiler, cannot run {}", cc);
+    }
+    command.arg("-I").arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("include"));
+    command
+}
+
+#[test]
+fn test_c_program() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .current_dir(root)
+        .args(&["rustc", "--lib", "--features", "capi",
+                "--crate-type", "staticlib", "--target-dir"])
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to build the static library");
+
+    let exe = out.join("capi-test");
+    let status = cc()
+        .arg(root.join("tests/capi.c"))
+        .arg(out.join("debug/libblake2_rfc.a"))
+        .args(&["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&exe)
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to compile the C program");
+
+    assert!(Command::new(&exe).status().unwrap().success());
+}
+
+/// Defines the size and alignment of a type, and the offset and size of
+/// its fields, as `RUST_*` macros for `capi_layout.c`.
+macro_rules! layout {
+    ($defs:ident, $ty:ident { $($field:ident),* }) => {{
+        $defs.push((concat!("SIZEOF_", stringify!($ty)), mem::size_of::<$ty>()));
+        $defs.push((concat!("ALIGNOF_", stringify!($ty)), mem::align_of::<$ty>()));
+        $(
+            let value: $ty = unsafe { mem::zeroed() };
+            $defs.push((concat!("OFFSETOF_", stringify!($ty), "_", stringify!($field)),
+                        mem::offset_of!($ty, $field)));
+            $defs.push((concat!("SIZEOF_", stringify!($ty), "_", stringify!($field)),
+                        mem::size_of_val(&{ value.$field })));
+        )*
+    }};
+}
+
+#[test]
+fn test_header_layout() {
+    let mut defs = Vec::new();
+    layout!(defs, blake2s_state {});
+    layout!(defs, blake2b_state {});
+    layout!(defs, blake2s_param {
+        digest_length, key_length, fanout, depth, leaf_length, node_offset,
+        node_depth, inner_length, salt, personal
+    });
+    layout!(defs, blake2b_param {
+        digest_length, key_length, fanout, depth, leaf_length, node_offset,
+        node_depth, inner_length, reserved, salt, personal
+    });
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let mut command = cc();
+    for (name, value) in defs {
+        command.arg(format!("-DRUST_{}={}", name, value));
+    }
+    let status = command
+        .args(&["-std=c11", "-c"])
+        .arg(root.join("tests/capi_layout.c"))
+        .arg("-o")
+        .arg(out.join("capi_layout.o"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "the layout of blake2.h differs from the Rust types");
+}
+
+/// Checks that the functions have the types declared in `blake2.h`.
+#[test]
+fn test_header_functions() {
+    type Init<S> = unsafe extern "C" fn(*mut S, usize) -> c_int;
+    type InitKey<S> = unsafe extern "C" fn(*mut S, usize, *const c_void, usize) -> c_int;
+    type InitParam<S, P> = unsafe extern "C" fn(*mut S, *const P) -> c_int;
+    type Update<S> = unsafe extern "C" fn(*mut S, *const u8, usize) -> c_int;
+    type Final<S> = unsafe extern "C" fn(*mut S, *mut u8, usize) -> c_int;
+    type OneShot = unsafe extern "C" fn(*mut u8, *const c_void, *const c_void,
+                                        usize, usize, usize) -> c_int;
+
+    let _: Init<blake2s_state> = blake2s_init;
+    let _: InitKey<blake2s_state> = blake2s_init_key;
+    let _: InitParam<blake2s_state, blake2s_param> = blake2s_init_param;
+    let _: Update<blake2s_state> = blake2s_update;
+    let _: Final<blake2s_state> = blake2s_final;
+
+    let _: Init<blake2b_state> = blake2b_init;
+    let _: InitKey<blake2b_state> = blake2b_init_key;
+    let _: InitParam<blake2b_state, blake2b_param> = blake2b_init_param;
+    let _: Update<blake2b_state> = blake2b_update;
+    let _: Final<blake2b_state> = blake2b_final;
+
+    let _: OneShot = blake2s;
+    let _: OneShot = blake2b;
+    let _: OneShot = blake2sp;
+    let _: OneShot = blake2bp;
+}
@@ -0,0 +1,56 @@
+/*
+ * Copyright 2015 blake2-rfc Developers
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+/*
+ * Checks the types of blake2.h against the types of src/capi.rs, whose
+ * sizes, alignments and field offsets are defined by tests/capi.rs as
+ * RUST_* macros. It only needs to compile.
+ */
+
+#include <stddef.h>
+
+#include "blake2.h"
+
+#define CHECK_TYPE(t)                                                   \
+  _Static_assert(sizeof(t) == RUST_SIZEOF_##t, "size of " #t);          \
+  _Static_assert(_Alignof(t) == RUST_ALIGNOF_##t, "alignment of " #t)
+
+#define CHECK_FIELD(t, f)                                               \
+  _Static_assert(offsetof(t, f) == RUST_OFFSETOF_##t##_##f,             \
+                 "offset of " #t "." #f);                               \
+  _Static_assert(sizeof(((t *)0)->f) == RUST_SIZEOF_##t##_##f,          \
+                 "size of " #t "." #f)
+
+CHECK_TYPE(blake2s_state);
+CHECK_TYPE(blake2b_state);
+
+CHECK_TYPE(blake2s_param);
+CHECK_FIELD(blake2s_param, digest_length);
+CHECK_FIELD(blake2s_param, key_length);
+CHECK_FIELD(blake2s_param, fanout);
+CHECK_FIELD(blake2s_param, depth);
+CHECK_FIELD(blake2s_param, leaf_length);
+CHECK_FIELD(blake2s_param, node_offset);
+CHECK_FIELD(blake2s_param, node_depth);
+CHECK_FIELD(blake2s_param, inner_length);
+CHECK_FIELD(blake2s_param, salt);
+CHECK_FIELD(blake2s_param, personal);
+
+CHECK_TYPE(blake2b_param);
+CHECK_FIELD(blake2b_param, digest_length);
+CHECK_FIELD(blake2b_param, key_length);
+CHECK_FIELD(blake2b_param, fanout);
+CHECK_FIELD(blake2b_param, depth);
+CHECK_FIELD(blake2b_param, leaf_length);
+CHECK_FIELD(blake2b_param, node_offset);
+CHECK_FIELD(blake2b_param, node_depth);
+CHECK_FIELD(blake2b_param, inner_length);
+CHECK_FIELD(blake2b_param, reserved);
+CHECK_FIELD(blake2b_param, salt);
+CHECK_FIELD(blake2b_param, personal);
@@ -0,0 +1,61 @@
+// Copyright 2015 blake2-rfc Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Builds the library with the `small` feature, and checks the code size
+//! of the compression functions.
+//!
+//! This test needs `nm` (or the tool named by `NM`), and fails without it.
+
+#![cfg(feature = "small")]
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Largest code size of a compression function, with its G function.
+const MAX_COMPRESS_SIZE: u64 = 1536;
+
+#[test]
+fn test_compress_size() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("size");
+    let obj = out.join("blake2_rfc.o");
+
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .current_dir(root)
+        .args(&["rustc", "--lib", "--release", "--no-default-features",
+                "--features", "small", "--target-dir"])
+        .arg(&out)
+        .arg("--")
+        .arg(format!("--emit=obj={}", obj.display()))
+        .args(&["-C", "codegen-units=1"])
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to build the library");
+
+    let nm = env::var("NM").unwrap_or_else(|_| "nm".into());
+    let output = match Command::new(&nm).args(&["--print-size", "-C"]).arg(&obj).output() {
+        Ok(output) => output,
+        Err(e) => panic!("the size test needs nm, cannot run {}: {}", nm, e),
+    };
+    assert!(output.status.success(), "failed to list the symbols");
+    let symbols = String::from_utf8(output.stdout).unwrap();
+
+    for state in &["Blake2b", "Blake2s"] {
+        let mut size = 0;
+        for line in symbols.lines() {
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            if fields.len() == 4 && ["compress_block", "compress_words", "g"].iter().any(
+                    |f| fields[3].ends_with(&format!("::{}::{}", state, f))) {
+                size += u64::from_str_radix(fields[1], 16).unwrap();
+            }
+        }
+        assert!(size > 0, "compression function of {} not found", state);
+        assert!(size <= MAX_COMPRESS_SIZE,
+                "compression function of {} is {} bytes", state, size);
+    }
+}